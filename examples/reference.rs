@@ -0,0 +1,45 @@
+use anyhow::{Context, Result};
+use obsim::nucleotide::{NucleotideGenome, K80};
+use obsim::simple::SimpleDisease;
+use obsim::simulate::{rounded_poisson, simulate_outbreak};
+use rand_distr::Gamma;
+use std::fs::File;
+use std::io::BufReader;
+
+// alternative RNG for reproducibility:
+// use rand::SeedableRng;
+// use rand_xoshiro::Xoshiro256PlusPlus;
+
+// Run with the path to a reference genome, e.g.
+// `cargo run --example reference -- MN908947.3.fasta > simulation.fa`
+fn main() -> Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .context("provide the path to a reference FASTA file")?;
+
+    let disease_model = SimpleDisease {
+        incubation_time: rounded_poisson(2.)?,
+        reporting_time: rounded_poisson(1.)?,
+        reproduction_number: Gamma::new(2.8, 0.3)?,
+        infectiousness: vec![0.34, 0.33, 0.33],
+    };
+
+    // expected mutations per time step
+    let mutation_rate = 1e-3 / 365.0 * 29903.0;
+
+    // halt the simulation if there are more than this number of cases
+    let max_cases = 200;
+
+    let mut rng = rand::thread_rng();
+    // use this instead for reproducible simulation:
+    // let mut rng = Xoshiro256PlusPlus::seed_from_u64(9948901_u64);
+
+    // transitions are about 3 times as likely as transversions
+    let model = K80::new(3.0)?;
+    let genome = NucleotideGenome::from_fasta(BufReader::new(File::open(path)?), &model)?;
+    let ob = simulate_outbreak(genome, &disease_model, mutation_rate, max_cases, &mut rng)?;
+
+    let stdout = std::io::stdout();
+    ob.write_fasta(stdout.lock())?;
+    Ok(())
+}
@@ -0,0 +1,466 @@
+//! Run a simulation described by a configuration file.
+//!
+//! See `examples/simple.toml` and `examples/binned.yaml` for annotated configurations.
+
+use clap::Parser;
+use obsim::covid::Covid;
+use obsim::nucleotide::{Jc69, NucleotideGenome};
+use obsim::simple::{DynSimpleGenome, SimpleDisease};
+use obsim::simulate::{binned_outbreaks, rounded_poisson, simulate_outbreak, BinnedOutbreakConfig};
+use obsim::{DiseaseModel, Genome, Outbreak};
+use rand::{Rng, SeedableRng};
+use rand_distr::Gamma;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::Deserialize;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Simulate outbreaks from a configuration file.
+///
+/// The sampled genomes are written in FASTA format, to standard output unless a path is given.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Simulation configuration in TOML or YAML (.yaml or .yml) format.
+    config: PathBuf,
+
+    /// Write the sampled genomes in FASTA format to this path.
+    #[arg(short, long)]
+    fasta: Option<PathBuf>,
+
+    /// Write a line list of the cases to this path.
+    ///
+    /// The line list is tab-separated if the path ends in .tsv, and comma-separated otherwise.
+    #[arg(short, long)]
+    line_list: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    /// Seed for the random number generator, or `None` for a different simulation on each run.
+    seed: Option<u64>,
+
+    /// Expected mutations per time step.
+    mutation_rate: f64,
+
+    disease: DiseaseConfig,
+    genome: GenomeConfig,
+    simulation: SimulationConfig,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "model", rename_all = "snake_case", deny_unknown_fields)]
+enum DiseaseConfig {
+    /// See [`SimpleDisease`].
+    Simple {
+        /// Mean of the Poisson-distributed incubation time.
+        incubation_time: f64,
+
+        /// Mean of the Poisson-distributed time from symptom onset to notification.
+        reporting_time: f64,
+
+        reproduction_number: GammaConfig,
+        infectiousness: Vec<f64>,
+    },
+
+    /// See [`Covid`].
+    Covid {
+        /// Mean of the Poisson-distributed time from symptom onset to notification.
+        reporting_time: f64,
+
+        reproduction_number: GammaConfig,
+
+        #[serde(default)]
+        variant: CovidVariant,
+    },
+}
+
+/// Parameter presets for [`Covid`].
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum CovidVariant {
+    /// See [`Covid::new`].
+    #[default]
+    Ancestral,
+
+    /// See [`Covid::delta`].
+    Delta,
+
+    /// See [`Covid::omicron`].
+    Omicron,
+}
+
+/// Parameters of a [`Gamma`] distribution.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct GammaConfig {
+    shape: f64,
+    scale: f64,
+}
+
+impl GammaConfig {
+    fn build(&self) -> Result<Gamma<f64>, rand_distr::GammaError> {
+        Gamma::new(self.shape, self.scale)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum GenomeConfig {
+    /// See [`DynSimpleGenome`].
+    Simple { length: usize },
+
+    /// See [`NucleotideGenome`], evolving under the Jukes-Cantor model.
+    ///
+    /// The index genome is either a random sequence of `length` bases, or the first record of the
+    /// FASTA file at `reference`.
+    Nucleotide {
+        length: Option<usize>,
+        reference: Option<PathBuf>,
+    },
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+enum SimulationConfig {
+    /// A single outbreak, see [`simulate_outbreak`].
+    Single { max_cases: u32 },
+
+    /// Many outbreaks grouped by size, see [`binned_outbreaks`].
+    Binned(BinnedOutbreakConfig),
+}
+
+impl Config {
+    fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        let config: Config = match path.extension().and_then(|x| x.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(&text)?,
+            _ => toml::from_str(&text)?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the parameters that would otherwise panic during the simulation.
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if !(self.mutation_rate >= 0.0 && self.mutation_rate.is_finite()) {
+            return Err("mutation_rate must be finite and non-negative".into());
+        }
+        self.disease.validate()?;
+        if let SimulationConfig::Binned(binned_config) = &self.simulation {
+            binned_config.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl DiseaseConfig {
+    /// Check that the disease model can be built and that its infectiousness is valid.
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let (reporting_time, reproduction_number) = match self {
+            DiseaseConfig::Simple {
+                incubation_time,
+                reporting_time,
+                reproduction_number,
+                infectiousness,
+            } => {
+                rounded_poisson(*incubation_time)
+                    .map_err(|err| format!("incubation_time: {}", err))?;
+                if infectiousness.is_empty()
+                    || infectiousness.iter().any(|x| !(*x >= 0.0 && x.is_finite()))
+                {
+                    return Err(
+                        "infectiousness must be a non-empty list of finite, non-negative values"
+                            .into(),
+                    );
+                }
+                (reporting_time, reproduction_number)
+            }
+            DiseaseConfig::Covid {
+                reporting_time,
+                reproduction_number,
+                ..
+            } => (reporting_time, reproduction_number),
+        };
+        rounded_poisson(*reporting_time).map_err(|err| format!("reporting_time: {}", err))?;
+        reproduction_number
+            .build()
+            .map_err(|err| format!("reproduction_number: {}", err))?;
+        Ok(())
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(err) = run(&args) {
+        eprintln!("obsim: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let config = Config::read(&args.config)
+        .map_err(|err| format!("invalid configuration {}: {}", args.config.display(), err))?;
+    let mut rng = match config.seed {
+        Some(seed) => Xoshiro256PlusPlus::seed_from_u64(seed),
+        None => Xoshiro256PlusPlus::from_entropy(),
+    };
+
+    let outbreak = match &config.disease {
+        DiseaseConfig::Simple {
+            incubation_time,
+            reporting_time,
+            reproduction_number,
+            infectiousness,
+        } => {
+            let disease_model = SimpleDisease {
+                incubation_time: rounded_poisson(*incubation_time)?,
+                reporting_time: rounded_poisson(*reporting_time)?,
+                reproduction_number: reproduction_number.build()?,
+                infectiousness: infectiousness.clone(),
+            };
+            with_genome(&config, &disease_model, &mut rng)?
+        }
+        DiseaseConfig::Covid {
+            reporting_time,
+            reproduction_number,
+            variant,
+        } => {
+            let reporting_time = rounded_poisson(*reporting_time)?;
+            let reproduction_number = reproduction_number.build()?;
+            let disease_model = match variant {
+                CovidVariant::Ancestral => Covid::new(reporting_time, reproduction_number),
+                CovidVariant::Delta => Covid::delta(reporting_time, reproduction_number),
+                CovidVariant::Omicron => Covid::omicron(reporting_time, reproduction_number),
+            };
+            with_genome(&config, &disease_model, &mut rng)?
+        }
+    };
+
+    match &args.fasta {
+        Some(path) => outbreak.write_fasta(&mut BufWriter::new(create(path)?))?,
+        None => outbreak.write_fasta(&mut io::stdout().lock())?,
+    }
+    if let Some(path) = &args.line_list {
+        let mut writer = BufWriter::new(create(path)?);
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("tsv") => outbreak.write_tsv(&mut writer)?,
+            _ => outbreak.write_csv(&mut writer)?,
+        }
+    }
+    Ok(())
+}
+
+/// Simulated outbreak with the genome type erased.
+///
+/// Only the outputs are needed once the simulation has finished.
+trait Output {
+    fn write_fasta(&self, writer: &mut dyn Write) -> io::Result<()>;
+    fn write_csv(&self, writer: &mut dyn Write) -> io::Result<()>;
+    fn write_tsv(&self, writer: &mut dyn Write) -> io::Result<()>;
+}
+
+impl<G: Genome> Output for Outbreak<G> {
+    fn write_fasta(&self, writer: &mut dyn Write) -> io::Result<()> {
+        Outbreak::write_fasta(self, writer)
+    }
+
+    fn write_csv(&self, writer: &mut dyn Write) -> io::Result<()> {
+        Outbreak::write_csv(self, writer)
+    }
+
+    fn write_tsv(&self, writer: &mut dyn Write) -> io::Result<()> {
+        Outbreak::write_tsv(self, writer)
+    }
+}
+
+fn with_genome<D, R>(
+    config: &Config,
+    disease_model: &D,
+    mut rng: R,
+) -> Result<Box<dyn Output>, Box<dyn Error>>
+where
+    D: DiseaseModel,
+    R: Rng,
+{
+    match &config.genome {
+        GenomeConfig::Simple { length } => {
+            let genome = DynSimpleGenome::new(*length);
+            simulate(config, genome, disease_model, rng)
+        }
+        GenomeConfig::Nucleotide { length, reference } => {
+            let genome = match (length, reference) {
+                (Some(length), None) => NucleotideGenome::random(*length, &Jc69, &mut rng),
+                (None, Some(path)) => {
+                    let reader = BufReader::new(File::open(path)?);
+                    NucleotideGenome::from_fasta(reader, &Jc69)
+                        .map_err(|err| format!("reading {}: {}", path.display(), err))?
+                }
+                _ => return Err("nucleotide genome needs one of `length` or `reference`".into()),
+            };
+            simulate(config, genome, disease_model, rng)
+        }
+    }
+}
+
+fn simulate<D, G, R>(
+    config: &Config,
+    genome: G,
+    disease_model: &D,
+    mut rng: R,
+) -> Result<Box<dyn Output>, Box<dyn Error>>
+where
+    D: DiseaseModel,
+    G: Genome + 'static,
+    R: Rng,
+{
+    let outbreak = match &config.simulation {
+        SimulationConfig::Single { max_cases } => simulate_outbreak(
+            genome,
+            disease_model,
+            config.mutation_rate,
+            *max_cases,
+            &mut rng,
+        )
+        .unwrap_or_else(|err| {
+            eprintln!("obsim: warning: {}; writing the cases so far", err);
+            *err.outbreak
+        }),
+        SimulationConfig::Binned(binned_config) => binned_outbreaks(
+            genome,
+            disease_model,
+            config.mutation_rate,
+            binned_config,
+            &mut rng,
+        )?,
+    };
+    Ok(Box::new(outbreak))
+}
+
+fn create(path: &Path) -> Result<File, String> {
+    File::create(path).map_err(|err| format!("creating {}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_config(config: Config) -> String {
+        let rng = Xoshiro256PlusPlus::seed_from_u64(config.seed.unwrap());
+        let disease_model = match &config.disease {
+            DiseaseConfig::Simple {
+                incubation_time,
+                reporting_time,
+                reproduction_number,
+                infectiousness,
+            } => SimpleDisease {
+                incubation_time: rounded_poisson(*incubation_time).unwrap(),
+                reporting_time: rounded_poisson(*reporting_time).unwrap(),
+                reproduction_number: reproduction_number.build().unwrap(),
+                infectiousness: infectiousness.clone(),
+            },
+            _ => panic!("expected a simple disease model"),
+        };
+        let outbreak = with_genome(&config, &disease_model, rng).unwrap();
+        let mut out = Vec::new();
+        outbreak.write_csv(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_example_configs() {
+        let config: Config = toml::from_str(include_str!("../../examples/simple.toml")).unwrap();
+        assert!(matches!(config.genome, GenomeConfig::Simple { length: 64 }));
+        assert!(run_config(config).starts_with("case,parent"));
+
+        let config: Config =
+            serde_yaml::from_str(include_str!("../../examples/binned.yaml")).unwrap();
+        assert!(matches!(config.simulation, SimulationConfig::Binned(_)));
+        assert!(run_config(config).starts_with("case,parent"));
+    }
+
+    #[test]
+    fn test_invalid_bins() {
+        let dir = std::env::temp_dir().join(format!("obsim-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("binned.yaml");
+        let text = include_str!("../../examples/binned.yaml");
+        fs::write(
+            &path,
+            text.replace("bad_simulation_cap: 1000", "bad_simulation_cap: 0"),
+        )
+        .unwrap();
+
+        let err = Config::read(&path).unwrap_err();
+        assert_eq!(err.to_string(), "bad_simulation_cap must be at least one");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_mutation_rate() {
+        let text = include_str!("../../examples/simple.toml");
+        for rate in ["-0.1", "nan", "inf"] {
+            let invalid = text.replace(
+                "mutation_rate = 0.0164",
+                &format!("mutation_rate = {}", rate),
+            );
+            let config: Config = toml::from_str(&invalid).unwrap();
+            let err = config.validate().unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "mutation_rate must be finite and non-negative"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_disease() {
+        let text = include_str!("../../examples/simple.toml");
+        for infectiousness in ["[]", "[0.5, -0.1]", "[0.5, nan]", "[inf]"] {
+            let invalid = text.replace(
+                "infectiousness = [0.34, 0.33, 0.33]",
+                &format!("infectiousness = {}", infectiousness),
+            );
+            let config: Config = toml::from_str(&invalid).unwrap();
+            let err = config.validate().unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "infectiousness must be a non-empty list of finite, non-negative values"
+            );
+        }
+
+        for (from, to, field) in [
+            (
+                "incubation_time = 2.0",
+                "incubation_time = -1.0",
+                "incubation_time",
+            ),
+            (
+                "reporting_time = 1.0",
+                "reporting_time = nan",
+                "reporting_time",
+            ),
+            ("shape = 2.5", "shape = 0.0", "reproduction_number"),
+        ] {
+            let config: Config = toml::from_str(&text.replace(from, to)).unwrap();
+            let err = config.validate().unwrap_err().to_string();
+            assert!(err.starts_with(&format!("{}: ", field)), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let text = include_str!("../../examples/simple.toml").replace("max_cases", "max_case");
+        assert!(toml::from_str::<Config>(&text).is_err());
+
+        let text = include_str!("../../examples/binned.yaml").replace(
+            "bad_simulation_cap: 1000",
+            "bad_simulation_cap: 1000\n  bad_simulaton_cap: 10",
+        );
+        assert!(serde_yaml::from_str::<Config>(&text).is_err());
+    }
+}
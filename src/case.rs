@@ -1,13 +1,22 @@
 //! Case-level information for use in models.
 
+use crate::genome::Genome;
 use crate::Time;
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use rand_distr::Distribution;
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
 
 /// Information generated about a case.
 ///
 /// This type is only relevant when implementing disease models.
 ///
-/// Times should be provided relative to the exposure time of the case.
-#[derive(Debug)]
+/// Times should be provided relative to the exposure time of the case. Struct literals can end
+/// with `..Default::default()` to leave the remaining times unset.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CaseHistory {
     /// Defines how the infectiousness of the case changes over time.
     ///
@@ -24,6 +33,42 @@ pub struct CaseHistory {
 
     /// Time after exposure when the case is reported.
     pub reported: Option<Time>,
+
+    /// Time after exposure when a specimen is collected for sequencing.
+    ///
+    /// When this is `None`, the case is sampled at the time it is reported.
+    pub sampled: Option<Time>,
+}
+
+/// Information generated about a case in continuous time.
+///
+/// This type is only relevant when implementing a
+/// [`ContinuousDiseaseModel`](crate::ContinuousDiseaseModel).
+///
+/// Times should be provided relative to the exposure time of the case.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContinuousCaseHistory {
+    /// Defines how the infectiousness of the case changes over time.
+    ///
+    /// The infectiousness is interpolated linearly between these `(time, rate)` points, which
+    /// must be in order of time and have non-negative rates, and is zero outside of them. A case
+    /// infectious at a constant rate of one from time 2 to 5 is `[(2.0, 1.0), (5.0, 1.0)]`.
+    ///
+    /// The total area under the curve is equal to the expected number of new individuals that
+    /// this case will infect.
+    pub infectiousness: Vec<(f64, f64)>,
+
+    /// Time after exposure when symptom onset occurs.
+    pub symptom_onset: Option<f64>,
+
+    /// Time after exposure when the case is reported.
+    pub reported: Option<f64>,
+
+    /// Time after exposure when a specimen is collected for sequencing.
+    ///
+    /// When this is `None`, the case is sampled at the time it is reported.
+    pub sampled: Option<f64>,
 }
 
 #[derive(Debug)]
@@ -33,28 +78,79 @@ pub(crate) enum Case {
     Recovered,
 }
 
+/// Implemented by the types of time in a [`History`].
+///
+/// These are integer time steps for [`simulate_outbreak`](crate::simulate_outbreak), and
+/// continuous `f64` times for [`simulate_continuous`](crate::simulate::simulate_continuous).
+pub trait Timestamp:
+    Copy
+    + Default
+    + PartialOrd
+    + Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + AddAssign
+    + SubAssign
+{
+    /// Evolve a genome over an elapsed interval of time.
+    fn mutate<G: Genome, R: Rng>(genome: &G, elapsed: Self, mutation_rate: f64, rng: R) -> G;
+
+    /// Total ordering of times, which is defined even for times that are not comparable.
+    fn cmp_total(&self, other: &Self) -> Ordering;
+}
+
+/// Time steps, mutated with [`Genome::mutate_time`].
+impl Timestamp for Time {
+    #[inline]
+    fn mutate<G: Genome, R: Rng>(genome: &G, elapsed: Self, mutation_rate: f64, rng: R) -> G {
+        genome.mutate_time(elapsed, mutation_rate, rng)
+    }
+
+    #[inline]
+    fn cmp_total(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+/// Continuous times, mutated with [`Genome::mutate_interval`].
+impl Timestamp for f64 {
+    #[inline]
+    fn mutate<G: Genome, R: Rng>(genome: &G, elapsed: Self, mutation_rate: f64, rng: R) -> G {
+        genome.mutate_interval(elapsed, mutation_rate, rng)
+    }
+
+    #[inline]
+    fn cmp_total(&self, other: &Self) -> Ordering {
+        self.total_cmp(other)
+    }
+}
+
 /// Important events in the disease history of a case.
 ///
 /// These are times relative to the start of the outbreak.
 #[derive(Debug)]
-pub struct History {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct History<T = Time> {
     /// Time when a case was initially infected.
-    pub infected: Time,
+    pub infected: T,
 
     /// Time when the case became infectious.
-    pub infectious_onset: Time,
+    pub infectious_onset: T,
 
     /// Time when the case was most infectious.
-    pub infectious_peak: Time,
+    pub infectious_peak: T,
 
     /// Time when the case recovered.
-    pub recovered: Time,
+    pub recovered: T,
 
     /// Time when the case would have been reported.
-    pub reported: Option<Time>,
+    pub reported: Option<T>,
 
     /// Time when symptoms began, if at all.
-    pub symptom_onset: Option<Time>,
+    pub symptom_onset: Option<T>,
+
+    /// Time when the case was sampled for sequencing, if at all.
+    pub sampled: Option<T>,
 }
 
 impl CaseHistory {
@@ -70,6 +166,7 @@ impl CaseHistory {
                 recovered: milestones[2],
                 reported: self.reported,
                 symptom_onset: self.symptom_onset,
+                sampled: self.sampled.or(self.reported),
             },
         )
     }
@@ -141,34 +238,118 @@ impl Case {
     }
 }
 
-impl History {
-    pub(crate) fn time_shift_forward(&mut self, offset: Time) {
+impl ContinuousCaseHistory {
+    /// The expected number of infections, which is the area under the infectiousness curve.
+    pub(crate) fn reproduction_number(&self) -> f64 {
+        self.infectiousness
+            .windows(2)
+            .map(|x| trapezoid(x[0], x[1]))
+            .sum()
+    }
+
+    /// Whether the infectiousness points are in order of time, with finite times and finite,
+    /// non-negative rates.
+    pub(crate) fn is_valid(&self) -> bool {
+        let finite = self
+            .infectiousness
+            .iter()
+            .all(|&(t, y)| t.is_finite() && y.is_finite() && y >= 0.0);
+        finite && self.infectiousness.windows(2).all(|x| x[0].0 <= x[1].0)
+    }
+
+    /// Draw a time of infection from the infectiousness curve, relative to exposure.
+    ///
+    /// The curve must be valid and have a positive area.
+    pub(crate) fn sample_infection<R: Rng>(&self, mut rng: R) -> f64 {
+        let areas: Vec<f64> = self
+            .infectiousness
+            .windows(2)
+            .map(|x| trapezoid(x[0], x[1]))
+            .collect();
+        let segment = WeightedIndex::new(&areas).unwrap().sample(&mut rng);
+        let ((t0, y0), (t1, y1)) = (
+            self.infectiousness[segment],
+            self.infectiousness[segment + 1],
+        );
+
+        // invert the quadratic cumulative area within the segment
+        let u: f64 = rng.gen();
+        let width = t1 - t0;
+        if (y1 - y0).abs() <= f64::EPSILON * y0.max(y1) {
+            t0 + u * width
+        } else {
+            t0 + width * ((y0 * y0 + u * (y1 * y1 - y0 * y0)).sqrt() - y0) / (y1 - y0)
+        }
+    }
+
+    pub(crate) fn into_history(self) -> History<f64> {
+        let segments = || {
+            self.infectiousness
+                .windows(2)
+                .filter(|x| trapezoid(x[0], x[1]) > 0.0)
+        };
+        let onset = segments().next().map_or(0.0, |x| x[0].0);
+        let recovered = segments().next_back().map_or(0.0, |x| x[1].0);
+        let peak = self
+            .infectiousness
+            .iter()
+            .fold(None, |peak: Option<(f64, f64)>, &x| match peak {
+                Some(peak) if peak.1 >= x.1 => Some(peak),
+                _ => Some(x),
+            })
+            .map_or(0.0, |x| x.0);
+
+        History {
+            infected: 0.0,
+            infectious_onset: onset,
+            infectious_peak: peak,
+            recovered,
+            reported: self.reported,
+            symptom_onset: self.symptom_onset,
+            sampled: self.sampled.or(self.reported),
+        }
+    }
+}
+
+/// Area under a straight line between two `(time, rate)` points.
+fn trapezoid((t0, y0): (f64, f64), (t1, y1): (f64, f64)) -> f64 {
+    (t1 - t0).max(0.0) * (y0 + y1) / 2.0
+}
+
+impl<T: Timestamp> History<T> {
+    pub(crate) fn time_shift_forward(&mut self, offset: T) {
         self.infected += offset;
         self.infectious_onset += offset;
         self.infectious_peak += offset;
         self.recovered += offset;
-        for time in &mut self.reported {
+        if let Some(time) = &mut self.reported {
             *time += offset;
         }
-        for time in &mut self.symptom_onset {
+        if let Some(time) = &mut self.symptom_onset {
+            *time += offset;
+        }
+        if let Some(time) = &mut self.sampled {
             *time += offset;
         }
     }
 
-    pub(crate) fn time_shift_back(&mut self, offset: Time) {
+    pub(crate) fn time_shift_back(&mut self, offset: T) {
         self.infected -= offset;
         self.infectious_onset -= offset;
         self.infectious_peak -= offset;
         self.recovered -= offset;
-        for time in &mut self.reported {
+        if let Some(time) = &mut self.reported {
+            *time -= offset;
+        }
+        if let Some(time) = &mut self.symptom_onset {
             *time -= offset;
         }
-        for time in &mut self.symptom_onset {
+        if let Some(time) = &mut self.sampled {
             *time -= offset;
         }
     }
 
-    pub(crate) fn iter(&self) -> impl Iterator<Item = Time> {
+    pub(crate) fn iter(&self) -> impl Iterator<Item = T> {
         [
             Some(self.infected),
             Some(self.infectious_onset),
@@ -176,6 +357,7 @@ impl History {
             Some(self.recovered),
             self.symptom_onset,
             self.reported,
+            self.sampled,
         ]
         .into_iter()
         .flatten()
@@ -185,6 +367,8 @@ impl History {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
 
     #[test]
     fn test_milestones() {
@@ -200,4 +384,43 @@ mod tests {
         let inf = &[0.1, 0.2, 0.6, 0.1, 0.0, 1.0];
         assert_eq!(milestones(inf), [0, 2, 4]);
     }
+
+    #[test]
+    fn test_continuous_history() {
+        let case = ContinuousCaseHistory {
+            infectiousness: vec![(0.0, 0.0), (1.0, 0.0), (2.0, 2.0), (4.0, 0.0), (5.0, 0.0)],
+            symptom_onset: Some(1.5),
+            reported: Some(3.0),
+            sampled: None,
+        };
+        assert_eq!(case.reproduction_number(), 3.0);
+        assert!(case.is_valid());
+
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let n = 10000;
+        let times: Vec<f64> = (0..n).map(|_| case.sample_infection(&mut rng)).collect();
+        assert!(times.iter().all(|t| (1.0..=4.0).contains(t)));
+        let before_peak = times.iter().filter(|t| **t < 2.0).count() as f64 / n as f64;
+        assert!((before_peak - 1.0 / 3.0).abs() < 0.02);
+
+        let history = case.into_history();
+        assert_eq!(history.infectious_onset, 1.0);
+        assert_eq!(history.infectious_peak, 2.0);
+        assert_eq!(history.recovered, 4.0);
+        assert_eq!(history.sampled, Some(3.0));
+
+        let invalid = [
+            vec![(0.0, 1.0), (2.0, 1.0), (1.0, 1.0)],
+            vec![(0.0, 1.0), (1.0, -1.0)],
+            vec![(0.0, 1.0), (f64::NAN, 1.0)],
+            vec![(0.0, f64::INFINITY), (1.0, 1.0)],
+        ];
+        for infectiousness in invalid {
+            let case = ContinuousCaseHistory {
+                infectiousness,
+                ..Default::default()
+            };
+            assert!(!case.is_valid());
+        }
+    }
 }
@@ -0,0 +1,147 @@
+//! Molecular clock models.
+//!
+//! A clock determines the mutation rate of each case. Anywhere that a simulation asks for a
+//! `mutation_rate`, a plain `f64` can be given for a strict clock where every lineage evolves at
+//! the same rate. A [`RelaxedClock`] instead draws a rate multiplier for each case.
+
+use rand::Rng;
+use rand_distr::{Distribution, Exp1, LogNormal, NormalError};
+
+/// Implemented by models of how the mutation rate varies between cases.
+///
+/// Rates are the expected number of mutations per unit time.
+pub trait MolecularClock {
+    /// The mutation rate averaged over lineages.
+    ///
+    /// This is used for ancestral lineages that are not part of a simulated outbreak.
+    fn mean_rate(&self) -> f64;
+
+    /// Choose the mutation rate of a new case.
+    ///
+    /// `parent_rate` is the rate of the case's infector, or `None` for an index case.
+    fn case_rate<R: Rng>(&self, parent_rate: Option<f64>, rng: R) -> f64;
+}
+
+/// Strict clock: all cases have the same mutation rate.
+impl MolecularClock for f64 {
+    #[inline]
+    fn mean_rate(&self) -> f64 {
+        *self
+    }
+
+    #[inline]
+    fn case_rate<R: Rng>(&self, _parent_rate: Option<f64>, _rng: R) -> f64 {
+        *self
+    }
+}
+
+impl<C: MolecularClock> MolecularClock for &C {
+    #[inline]
+    fn mean_rate(&self) -> f64 {
+        (*self).mean_rate()
+    }
+
+    #[inline]
+    fn case_rate<R: Rng>(&self, parent_rate: Option<f64>, rng: R) -> f64 {
+        (*self).case_rate(parent_rate, rng)
+    }
+}
+
+/// Relaxed clock: each case draws a random multiplier on the mean mutation rate.
+///
+/// When the clock is uncorrelated, each case's rate is `mean_rate` times an independent draw from
+/// `multiplier`. When it is autocorrelated, each case's rate is instead its infector's rate times
+/// a draw from `multiplier`, so that rates drift along transmission chains. Index cases always
+/// start from `mean_rate`.
+///
+/// The `multiplier` distribution should normally have a mean of one; see
+/// [`lognormal`](RelaxedClock::lognormal) and [`exponential`](RelaxedClock::exponential).
+#[derive(Debug, Clone, Copy)]
+pub struct RelaxedClock<D> {
+    /// Expected number of mutations per unit time, averaged over lineages.
+    pub mean_rate: f64,
+
+    /// A `Distribution<f64>` of rate multipliers.
+    pub multiplier: D,
+
+    /// Whether each case's rate is derived from its infector's rate.
+    pub autocorrelated: bool,
+}
+
+impl RelaxedClock<LogNormal<f64>> {
+    /// Uncorrelated clock with lognormal multipliers with mean one.
+    ///
+    /// `sigma` is the standard deviation of the log of the multipliers.
+    pub fn lognormal(mean_rate: f64, sigma: f64) -> Result<Self, NormalError> {
+        Ok(RelaxedClock {
+            mean_rate,
+            multiplier: LogNormal::new(-sigma * sigma / 2.0, sigma)?,
+            autocorrelated: false,
+        })
+    }
+}
+
+impl RelaxedClock<Exp1> {
+    /// Uncorrelated clock with exponential multipliers with mean one.
+    pub fn exponential(mean_rate: f64) -> Self {
+        RelaxedClock {
+            mean_rate,
+            multiplier: Exp1,
+            autocorrelated: false,
+        }
+    }
+}
+
+impl<D> RelaxedClock<D> {
+    /// Derive each case's rate from its infector's rate.
+    pub fn autocorrelated(self) -> Self {
+        RelaxedClock {
+            autocorrelated: true,
+            ..self
+        }
+    }
+}
+
+impl<D: Distribution<f64>> MolecularClock for RelaxedClock<D> {
+    #[inline]
+    fn mean_rate(&self) -> f64 {
+        self.mean_rate
+    }
+
+    fn case_rate<R: Rng>(&self, parent_rate: Option<f64>, mut rng: R) -> f64 {
+        let base = match parent_rate {
+            Some(rate) if self.autocorrelated => rate,
+            _ => self.mean_rate,
+        };
+        base * self.multiplier.sample(&mut rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    #[test]
+    fn test_lognormal_mean() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let clock = RelaxedClock::lognormal(2.0, 0.5).unwrap();
+        let n = 10000;
+        let mean = (0..n).map(|_| clock.case_rate(None, &mut rng)).sum::<f64>() / n as f64;
+        assert!((mean - 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_autocorrelated() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let clock = RelaxedClock {
+            mean_rate: 1.0,
+            multiplier: rand_distr::Uniform::new_inclusive(2.0, 2.0),
+            autocorrelated: false,
+        };
+        assert_eq!(clock.case_rate(Some(3.0), &mut rng), 2.0);
+        assert_eq!(clock.autocorrelated().case_rate(Some(3.0), &mut rng), 6.0);
+        assert_eq!(clock.autocorrelated().case_rate(None, &mut rng), 2.0);
+    }
+}
@@ -1,7 +1,9 @@
+pub mod continuous;
 pub mod covid;
+pub mod onset;
 pub mod simple;
 
-use crate::case::CaseHistory;
+use crate::case::{CaseHistory, ContinuousCaseHistory};
 use rand::Rng;
 
 /// Implemented by types that model the development of disease.
@@ -24,3 +26,15 @@ pub trait DiseaseModel {
         self.generate_case(&mut state, rng)
     }
 }
+
+/// Implemented by types that model the development of disease in continuous time.
+///
+/// This is the counterpart of [`DiseaseModel`] for
+/// [`simulate_continuous`](crate::simulate::simulate_continuous). The model generates the
+/// infectiousness of each case as a continuous curve, from which the simulation draws the exact
+/// times of transmission events.
+pub trait ContinuousDiseaseModel {
+    type State: Default;
+
+    fn generate_case<R: Rng>(&self, state: &mut Self::State, rng: R) -> ContinuousCaseHistory;
+}
@@ -0,0 +1,58 @@
+use super::ContinuousDiseaseModel;
+use crate::case::ContinuousCaseHistory;
+use rand::Rng;
+use rand_distr::Distribution;
+
+/// Simple outbreak model in continuous time.
+///
+/// This is the continuous-time counterpart of [`SimpleDisease`](super::simple::SimpleDisease).
+/// All cases have the same infectiousness profile. There is a random incubation time when the
+/// case is not infectious, and then the onset of symptoms and infectiousness occur
+/// simultaneously. Reporting occurs randomly after the onset time.
+///
+/// Each case is assigned a random reproduction number that is used to scale the infectiousness
+/// profile.
+///
+/// See [`rand_distr`] for some useful distributions, such as
+/// [`Gamma`](rand_distr::Gamma) and [`LogNormal`](rand_distr::LogNormal).
+#[derive(Debug)]
+pub struct ContinuousDisease<DInc, DRep, DR> {
+    /// A `Distribution<f64>` of times between infection and infectiousness/symptom onset.
+    pub incubation_time: DInc,
+
+    /// A `Distribution<f64>` of times between symptom onset and case notification.
+    pub reporting_time: DRep,
+
+    /// A `Distribution<f64>` of individual reproduction numbers.
+    pub reproduction_number: DR,
+
+    /// Infectiousness after onset as `(time, rate)` points that are interpolated linearly, as for
+    /// [`ContinuousCaseHistory::infectiousness`].
+    ///
+    /// Normally the area under the curve should be unity. It is multiplied by the case's
+    /// reproduction number to give the case's infectivity.
+    pub infectiousness: Vec<(f64, f64)>,
+}
+
+impl<DInc, DRep, DR> ContinuousDiseaseModel for ContinuousDisease<DInc, DRep, DR>
+where
+    DInc: Distribution<f64>,
+    DRep: Distribution<f64>,
+    DR: Distribution<f64>,
+{
+    type State = ();
+
+    fn generate_case<R: Rng>(&self, _state: &mut Self::State, mut rng: R) -> ContinuousCaseHistory {
+        let onset = self.incubation_time.sample(&mut rng);
+        let reported = onset + self.reporting_time.sample(&mut rng);
+        let r = self.reproduction_number.sample(&mut rng);
+        let infect = self.infectiousness.iter().map(|(t, x)| (onset + t, x * r));
+
+        ContinuousCaseHistory {
+            infectiousness: infect.collect(),
+            symptom_onset: Some(onset),
+            reported: Some(reported),
+            sampled: None,
+        }
+    }
+}
@@ -9,20 +9,48 @@ use rand_distr::Distribution;
 
 /// Covid-19 disease model.
 ///
-/// From exposure there is a 2 day latent period before the onset of infectiousness. This is
-/// followed by a 3 day exponential increase to peak infectiousness, then a linear decline over the
-/// following 12 days.
-///
-/// When cases do not have symptoms, their infectivity is multiplied by a factor of 0.3. For cases
-/// that do develop symptoms, the onset will occur within the first 3 days and last until the end
-/// of infectiousness.
+/// Each case has a baseline infectiousness curve over the days since exposure. When cases do not
+/// have symptoms, their infectivity is multiplied by a reduction factor. For cases that do develop
+/// symptoms, the onset day is drawn from a short distribution and symptoms last until the end of
+/// infectiousness.
 ///
-/// Based on the model used in
+/// [`Covid::new`] uses the parameters of the model in
 /// Chang, S.L., Harding, N., Zachreson, C. et al.
 /// Modelling transmission and control of the COVID-19 pandemic in Australia.
 /// _Nat Commun_ **11**, 5710 (2020).
 /// doi: [10.1038/s41467-020-19393-6](https://doi.org/10.1038/s41467-020-19393-6)
-#[derive(Debug)]
+///
+/// From exposure there is a 2 day latent period before the onset of infectiousness. This is
+/// followed by a 3 day exponential increase to peak infectiousness, then a linear decline over the
+/// following 10 days. Asymptomatic infectivity is reduced by a factor of 0.3, and symptom onset
+/// occurs within the first 3 days.
+///
+/// See also the [`delta`](Covid::delta) and [`omicron`](Covid::omicron) presets.
+///
+/// # Panics
+/// The parameters are public and not checked. Simulations panic if `fraction_symptomatic` is
+/// outside `[0, 1]`, if `symptom_onset` has no positive weight or any negative weight, or if the
+/// infectiousness or asymptomatic reduction give negative infectivity.
+///
+/// # Example
+/// ```
+/// use obsim::models::Covid;
+/// use obsim::simple::SimpleGenome;
+/// use obsim::simulate::{rounded_poisson, simulate_outbreak};
+/// use rand::SeedableRng;
+/// use rand_distr::Gamma;
+/// use rand_xoshiro::Xoshiro256PlusPlus;
+///
+/// let disease_model = Covid::omicron(rounded_poisson(1.).unwrap(), Gamma::new(4.0, 0.5).unwrap());
+///
+/// let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+/// let genome = SimpleGenome::<64>::default();
+/// // about 25 substitutions per year across the 30 kb genome
+/// let outbreak = simulate_outbreak(genome, &disease_model, 25.0 / 365.0, 100, &mut rng)
+///     .unwrap_or_else(|err| *err.outbreak);
+/// assert!(outbreak.n_cases() > 0);
+/// ```
+#[derive(Debug, Clone)]
 pub struct Covid<DRep, DR> {
     /// A `Distribution<Time>` of times between symptom onset and case notification.
     pub reporting_time: DRep,
@@ -34,26 +62,103 @@ pub struct Covid<DRep, DR> {
     /// transmission for the relevant period. This means that the actual number of cases will tend
     /// to be fewer than implied by this distribution alone.
     pub reproduction_number: DR,
+
+    /// Baseline infectiousness on each day since exposure, normalised to unity.
+    ///
+    /// Leading zeros give the latent period. See [`Covid::infectiousness_profile`].
+    pub infectiousness: Vec<f64>,
+
+    /// Fraction of baseline infectiousness when asymptomatic/pre-symptomatic, which must be
+    /// non-negative.
+    pub asymptomatic_infectiousness: f64,
+
+    /// Proportion of cases that will ever become symptomatic, which must be between 0 and 1.
+    pub fraction_symptomatic: f64,
+
+    /// Relative proportion of symptomatic cases that will have symptom onset on each day.
+    ///
+    /// e.g. `[3.0, 5.0, 2.0]` means 30% develop symptoms on the day of infection and 50% on the
+    /// following day. The weights must be non-negative and at least one must be positive.
+    pub symptom_onset: Vec<f64>,
 }
 
-/// Baseline infectiousness curve normalised to unity.
-#[rustfmt::skip]
-const INFECTIOUSNESS: &[f64] = &[
-    0.0, 0.0, // latent
-    0.04, 0.08, 0.16, // exponential growth
-    0.144, 0.128, 0.112, 0.096, 0.08, 0.064, 0.048, 0.032, 0.016, // linear drop
-];
+impl<DRep, DR> Covid<DRep, DR> {
+    /// Covid-19 model with the parameters of Chang et al. (2020).
+    pub fn new(reporting_time: DRep, reproduction_number: DR) -> Self {
+        Covid {
+            reporting_time,
+            reproduction_number,
+            infectiousness: Self::infectiousness_profile(2, 3, 10),
+            asymptomatic_infectiousness: 0.3,
+            fraction_symptomatic: 0.667,
+            symptom_onset: vec![3.0, 5.0, 2.0],
+        }
+    }
 
-/// Fraction of baseline infectiousness when asymptomatic/pre-symptomatic.
-const ASYMP_INFECT: f64 = 0.3;
+    /// Delta-like parameters, with a shorter latent period and faster decline.
+    ///
+    /// Virus was detectable about 4 days after exposure, compared with 6 days for earlier
+    /// lineages, in
+    /// Li, B., Deng, A., Li, K. et al. Viral infection and transmission in a large, well-traced
+    /// outbreak caused by the SARS-CoV-2 Delta variant. _Nat Commun_ **13**, 460 (2022).
+    /// doi: [10.1038/s41467-022-28089-y](https://doi.org/10.1038/s41467-022-28089-y)
+    ///
+    /// Infectiousness starts 1 day after exposure, peaks 3 days later and declines over the
+    /// following 8 days. The other parameters are as for [`Covid::new`].
+    pub fn delta(reporting_time: DRep, reproduction_number: DR) -> Self {
+        Covid {
+            infectiousness: Self::infectiousness_profile(1, 3, 8),
+            ..Self::new(reporting_time, reproduction_number)
+        }
+    }
 
-/// Proportion of cases that will ever become symptomatic.
-const FRAC_SYMPTOMATIC: f64 = 0.667;
+    /// Omicron-like parameters, with a shorter course of infection.
+    ///
+    /// The serial interval was about a day shorter than for Delta in
+    /// Backer, J.A., Eggink, D., Andeweg, S.P. et al. Shorter serial intervals in SARS-CoV-2
+    /// cases with Omicron BA.1 variant compared with Delta variant, the Netherlands, 13 to 26
+    /// December 2021. _Euro Surveill_ **27**(6), 2200042 (2022).
+    /// doi: [10.2807/1560-7917.ES.2022.27.6.2200042](https://doi.org/10.2807/1560-7917.ES.2022.27.6.2200042)
+    ///
+    /// The pooled percentage of asymptomatic infections was 32.40% (95% CI 25.30–39.51%) in the
+    /// abstract of
+    /// Shang, W., Kang, L., Cao, G. et al. Percentage of asymptomatic infections among SARS-CoV-2
+    /// Omicron variant-positive individuals: a systematic review and meta-analysis.
+    /// _Vaccines_ **10**(7), 1049 (2022).
+    /// doi: [10.3390/vaccines10071049](https://doi.org/10.3390/vaccines10071049)
+    ///
+    /// so `fraction_symptomatic` is 1 − 0.324 = 0.676. This is close to the 0.667 of
+    /// [`Covid::new`], so the proportion of asymptomatic cases is about the same.
+    ///
+    /// Infectiousness starts 1 day after exposure, peaks 2 days later and declines over the
+    /// following 7 days. Symptom onset is shifted earlier to match the shorter serial interval;
+    /// these curves are illustrative rather than fitted to the cited data.
+    pub fn omicron(reporting_time: DRep, reproduction_number: DR) -> Self {
+        Covid {
+            infectiousness: Self::infectiousness_profile(1, 2, 7),
+            fraction_symptomatic: 0.676,
+            symptom_onset: vec![5.0, 4.0, 1.0],
+            ..Self::new(reporting_time, reproduction_number)
+        }
+    }
 
-/// Relative proportion of symptomatic cases that will have symptom onset on each day.
-///
-/// i.e. 30% develop symptoms on the day of infection, 50% on the following day.
-const SYMPTOMS: [u32; 3] = [3, 5, 2];
+    /// Infectiousness curve with a latent period, exponential growth and linear decline.
+    ///
+    /// There are `latent` days of zero infectiousness, then `growth` days where infectiousness
+    /// doubles each day up to the peak, then infectiousness falls linearly to reach zero `decline`
+    /// days after the peak. The curve is normalised to unity.
+    pub fn infectiousness_profile(latent: usize, growth: usize, decline: usize) -> Vec<f64> {
+        let growth = (0..growth).map(|i| 0.5_f64.powi((growth - 1 - i) as i32));
+        let decline = (1..decline).map(|i| 1.0 - i as f64 / decline as f64);
+        let mut profile = vec![0.0; latent];
+        profile.extend(growth.chain(decline));
+        let total: f64 = profile.iter().sum();
+        for x in &mut profile {
+            *x /= total;
+        }
+        profile
+    }
+}
 
 impl<DRep, DR> DiseaseModel for Covid<DRep, DR>
 where
@@ -63,22 +168,28 @@ where
     type State = ();
 
     fn generate_case<R: Rng>(&self, _state: &mut Self::State, mut rng: R) -> CaseHistory {
-        let will_have_symptoms = rng.gen_bool(FRAC_SYMPTOMATIC);
+        let will_have_symptoms = rng.gen_bool(self.fraction_symptomatic);
         let symptom_onset: Option<Time> = will_have_symptoms.then(|| {
-            sample_weighted(&mut rng, SYMPTOMS.len(), |x| SYMPTOMS[x], 1)
-                .unwrap()
-                .index(0) as Time
+            sample_weighted(
+                &mut rng,
+                self.symptom_onset.len(),
+                |x| self.symptom_onset[x],
+                1,
+            )
+            .unwrap()
+            .index(0) as Time
         });
         let reported = symptom_onset.map(|x| x + self.reporting_time.sample(&mut rng));
 
-        let mut infectivity = INFECTIOUSNESS.to_vec();
+        let mut infectivity = self.infectiousness.clone();
 
         // reduce infectivity for pre-symptomatic period or is asymptomatic
         let end_pos = symptom_onset
             .map(|x| x as usize)
-            .unwrap_or(INFECTIOUSNESS.len());
+            .unwrap_or(infectivity.len())
+            .min(infectivity.len());
         for inf in &mut infectivity[0..end_pos] {
-            *inf *= ASYMP_INFECT;
+            *inf *= self.asymptomatic_infectiousness;
         }
 
         // scale by reproduction number
@@ -91,9 +202,38 @@ where
             infectivity,
             symptom_onset,
             reported,
+            sampled: None,
         }
     }
 
     // fn generate_singleton<R: Rng>(&self, mut rng: R) -> CaseHistory {
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chang_profile() {
+        #[rustfmt::skip]
+        let expected = [
+            0.0, 0.0, // latent
+            0.04, 0.08, 0.16, // exponential growth
+            0.144, 0.128, 0.112, 0.096, 0.08, 0.064, 0.048, 0.032, 0.016, // linear drop
+        ];
+        let profile = Covid::<(), ()>::infectiousness_profile(2, 3, 10);
+        assert_eq!(profile.len(), expected.len());
+        for (x, y) in profile.iter().zip(expected) {
+            assert!((x - y).abs() < 1e-12);
+        }
+        assert!(
+            (Covid::<(), ()>::infectiousness_profile(1, 2, 7)
+                .iter()
+                .sum::<f64>()
+                - 1.0)
+                .abs()
+                < 1e-12
+        );
+    }
+}
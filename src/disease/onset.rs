@@ -0,0 +1,342 @@
+//! Disease models where infectiousness is anchored to symptom onset.
+
+use super::DiseaseModel;
+use crate::case::CaseHistory;
+use crate::simulate::{rounded_poisson, RoundedPoisson};
+use crate::Time;
+use rand::Rng;
+use rand_distr::Distribution;
+
+/// Disease model where infectiousness is given relative to the day of symptom onset.
+///
+/// The time of symptom onset is drawn from the incubation time, and infectiousness starts
+/// `presymptomatic` days earlier. Asymptomatic cases follow the same timeline but have no symptom
+/// onset and are never reported. Any infectiousness that would fall before exposure is dropped.
+///
+/// Use the [`influenza`](OnsetDisease::influenza), [`measles`](OnsetDisease::measles),
+/// [`mpox`](OnsetDisease::mpox) and [`pertussis`](OnsetDisease::pertussis) presets for
+/// parameters from the published literature. The incubation times and symptomatic fractions of the
+/// presets come from the cited sources, but their daily infectiousness values are illustrative:
+/// they follow the infectious period described in each source and are not taken from a published
+/// table.
+///
+/// # Panics
+/// The parameters are public and not checked. Simulations panic if `fraction_symptomatic` is
+/// outside `[0, 1]`.
+///
+/// # Example
+/// ```
+/// use obsim::models::OnsetDisease;
+/// use obsim::simple::SimpleGenome;
+/// use obsim::simulate::{rounded_poisson, simulate_outbreak};
+/// use rand::SeedableRng;
+/// use rand_distr::Gamma;
+/// use rand_xoshiro::Xoshiro256PlusPlus;
+///
+/// // cases are notified about 2 days after symptom onset, and R has a mean of 1.3
+/// let disease_model =
+///     OnsetDisease::influenza(rounded_poisson(2.).unwrap(), Gamma::new(2.6, 0.5).unwrap());
+///
+/// let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+/// let genome = SimpleGenome::<64>::default();
+/// let outbreak = simulate_outbreak(genome, &disease_model, 0.01, 100, &mut rng)
+///     .unwrap_or_else(|err| *err.outbreak);
+/// assert!(outbreak.n_cases() > 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OnsetDisease<DInc, DRep, DR> {
+    /// A `Distribution<Time>` of times between infection and symptom onset.
+    pub incubation_time: DInc,
+
+    /// A `Distribution<Time>` of times between symptom onset and case notification.
+    pub reporting_time: DRep,
+
+    /// A `Distribution<f64>` of individual reproduction numbers of symptomatic cases.
+    pub reproduction_number: DR,
+
+    /// Relative infectiousness on each day, starting `presymptomatic` days before symptom onset.
+    ///
+    /// This should normally sum to unity.
+    pub infectiousness: Vec<f64>,
+
+    /// Number of days of infectiousness before symptom onset.
+    pub presymptomatic: Time,
+
+    /// Proportion of cases that will ever become symptomatic, which must be between 0 and 1.
+    pub fraction_symptomatic: f64,
+
+    /// Fraction of symptomatic infectiousness for asymptomatic cases.
+    pub asymptomatic_infectiousness: f64,
+}
+
+impl<DRep, DR> OnsetDisease<RoundedPoisson, DRep, DR> {
+    /// Seasonal influenza.
+    ///
+    /// Infectiousness starts the day before symptom onset, peaks on the day after onset and lasts
+    /// for about 5 days. Asymptomatic cases are assumed to be half as infectious.
+    ///
+    /// Uses a median incubation time of 1.4 days for influenza A from
+    /// Lessler, J., Reich, N.G., Brookmeyer, R. et al. Incubation periods of acute respiratory
+    /// viral infections: a systematic review. _Lancet Infect Dis_ **9**, 291–300 (2009).
+    /// doi: [10.1016/S1473-3099(09)70069-6](https://doi.org/10.1016/S1473-3099(09)70069-6)
+    ///
+    /// and the symptomatic fraction of 66.9% and the shedding timeline from
+    /// Carrat, F., Vergu, E., Ferguson, N.M. et al. Time lines of infection and disease in human
+    /// influenza: a review of volunteer challenge studies. _Am J Epidemiol_ **167**, 775–785
+    /// (2008). doi: [10.1093/aje/kwm375](https://doi.org/10.1093/aje/kwm375)
+    ///
+    /// The reproduction number of seasonal influenza has a median of about 1.3, see
+    /// Biggerstaff, M., Cauchemez, S., Reed, C. et al. Estimates of the reproduction number for
+    /// seasonal, pandemic, and zoonotic influenza: a systematic review of the literature.
+    /// _BMC Infect Dis_ **14**, 480 (2014).
+    /// doi: [10.1186/1471-2334-14-480](https://doi.org/10.1186/1471-2334-14-480)
+    pub fn influenza(reporting_time: DRep, reproduction_number: DR) -> Self {
+        OnsetDisease {
+            incubation_time: rounded_poisson(1.4).unwrap(),
+            reporting_time,
+            reproduction_number,
+            infectiousness: vec![0.15, 0.3, 0.25, 0.15, 0.1, 0.05],
+            presymptomatic: 1,
+            fraction_symptomatic: 0.669,
+            asymptomatic_infectiousness: 0.5,
+        }
+    }
+
+    /// Measles.
+    ///
+    /// Symptom onset is the start of the prodrome (fever, cough and conjunctivitis), with the rash
+    /// appearing about 3 days later. Cases are infectious from the day before prodrome onset,
+    /// which is about 4 days before the rash, until about 4 days after the rash. All infections
+    /// are symptomatic.
+    ///
+    /// Uses a median incubation time of 12.5 days from
+    /// Lessler, J., Reich, N.G., Brookmeyer, R. et al. Incubation periods of acute respiratory
+    /// viral infections: a systematic review. _Lancet Infect Dis_ **9**, 291–300 (2009).
+    /// doi: [10.1016/S1473-3099(09)70069-6](https://doi.org/10.1016/S1473-3099(09)70069-6)
+    ///
+    /// The basic reproduction number is commonly quoted as 12 to 18, but varies widely between
+    /// settings, see
+    /// Guerra, F.M., Bolotin, S., Lim, G. et al. The basic reproduction number (R0) of measles: a
+    /// systematic review. _Lancet Infect Dis_ **17**, e420–e428 (2017).
+    /// doi: [10.1016/S1473-3099(17)30307-9](https://doi.org/10.1016/S1473-3099(17)30307-9)
+    ///
+    /// In a partially vaccinated population the effective reproduction number is much lower.
+    ///
+    /// # Example
+    /// ```
+    /// use obsim::models::OnsetDisease;
+    /// use obsim::nucleotide::{Jc69, NucleotideGenome};
+    /// use obsim::simulate::{rounded_poisson, simulate_outbreak};
+    /// use rand::SeedableRng;
+    /// use rand_distr::Gamma;
+    /// use rand_xoshiro::Xoshiro256PlusPlus;
+    ///
+    /// // outbreak in a highly vaccinated population with a mean R of 1.2
+    /// let disease_model =
+    ///     OnsetDisease::measles(rounded_poisson(3.).unwrap(), Gamma::new(0.6, 2.0).unwrap());
+    ///
+    /// let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+    /// // N-450 genotyping region
+    /// let genome = NucleotideGenome::random(450, &Jc69, &mut rng);
+    /// let outbreak = simulate_outbreak(genome, &disease_model, 5e-4, 100, &mut rng)
+    ///     .unwrap_or_else(|err| *err.outbreak);
+    /// assert!(outbreak.n_cases() > 0);
+    /// ```
+    pub fn measles(reporting_time: DRep, reproduction_number: DR) -> Self {
+        OnsetDisease {
+            incubation_time: rounded_poisson(12.5).unwrap(),
+            reporting_time,
+            reproduction_number,
+            infectiousness: vec![0.05, 0.1, 0.15, 0.2, 0.2, 0.15, 0.08, 0.05, 0.02],
+            presymptomatic: 1,
+            fraction_symptomatic: 1.0,
+            asymptomatic_infectiousness: 0.0,
+        }
+    }
+
+    /// Mpox (clade IIb).
+    ///
+    /// Cases are infectious for up to 4 days before symptom onset, and remain infectious with
+    /// declining intensity while lesions heal over the following 2 weeks. All infections are
+    /// assumed to be symptomatic.
+    ///
+    /// Uses a mean incubation time of 7.6 days and the pre-symptomatic transmission window
+    /// estimated from the 2022 outbreak in
+    /// Ward, T., Christie, R., Paton, R.S. et al. Transmission dynamics of monkeypox in the United
+    /// Kingdom: contact tracing study. _BMJ_ **379**, e073153 (2022).
+    /// doi: [10.1136/bmj-2022-073153](https://doi.org/10.1136/bmj-2022-073153)
+    ///
+    /// # Example
+    /// ```
+    /// use obsim::models::OnsetDisease;
+    /// use obsim::simple::SimpleGenome;
+    /// use obsim::simulate::{rounded_poisson, simulate_outbreak};
+    /// use rand::SeedableRng;
+    /// use rand_distr::Gamma;
+    /// use rand_xoshiro::Xoshiro256PlusPlus;
+    ///
+    /// // cases are notified about 5 days after symptom onset, with overdispersed transmission
+    /// let disease_model =
+    ///     OnsetDisease::mpox(rounded_poisson(5.).unwrap(), Gamma::new(0.5, 3.0).unwrap());
+    ///
+    /// let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+    /// let genome = SimpleGenome::<64>::default();
+    /// // about 6 substitutions per year across the 197 kb genome
+    /// let outbreak = simulate_outbreak(genome, &disease_model, 6.0 / 365.0, 100, &mut rng)
+    ///     .unwrap_or_else(|err| *err.outbreak);
+    /// assert!(outbreak.n_cases() > 0);
+    /// ```
+    pub fn mpox(reporting_time: DRep, reproduction_number: DR) -> Self {
+        #[rustfmt::skip]
+        let infectiousness = vec![
+            0.06, 0.08, 0.1, 0.12, // pre-symptomatic
+            0.12, 0.1, 0.08, 0.07, 0.06, 0.05, 0.04, 0.03, 0.03, 0.02, 0.02, 0.01, 0.01, // lesions
+        ];
+        OnsetDisease {
+            incubation_time: rounded_poisson(7.6).unwrap(),
+            reporting_time,
+            reproduction_number,
+            infectiousness,
+            presymptomatic: 4,
+            fraction_symptomatic: 1.0,
+            asymptomatic_infectiousness: 0.0,
+        }
+    }
+
+    /// Pertussis (whooping cough).
+    ///
+    /// Symptom onset is the start of the catarrhal stage, which lasts 1 to 2 weeks and is the most
+    /// infectious period. Infectiousness then declines through the paroxysmal stage until about 3
+    /// weeks after onset. All infections are assumed to be symptomatic, although in practice mild
+    /// infections in previously vaccinated adults often go unrecognised.
+    ///
+    /// Uses the midpoint of the 7 to 10 day incubation time and the infectious period from
+    /// Hamborsky, J., Kroger, A. and Wolfe, S. (eds). _Epidemiology and Prevention of
+    /// Vaccine-Preventable Diseases_, 13th edn, chapter 16. Centers for Disease Control and
+    /// Prevention (2015).
+    ///
+    /// For estimates of reproduction numbers, see
+    /// Kretzschmar, M., Teunis, P.F.M. and Pebody, R.G. Incidence and reproduction numbers of
+    /// pertussis: estimates from serological and social contact data in five European countries.
+    /// _PLoS Med_ **7**, e1000291 (2010).
+    /// doi: [10.1371/journal.pmed.1000291](https://doi.org/10.1371/journal.pmed.1000291)
+    ///
+    /// # Example
+    /// ```
+    /// use obsim::models::OnsetDisease;
+    /// use obsim::simple::SimpleGenome;
+    /// use obsim::simulate::{rounded_poisson, simulate_outbreak};
+    /// use rand::SeedableRng;
+    /// use rand_distr::Gamma;
+    /// use rand_xoshiro::Xoshiro256PlusPlus;
+    ///
+    /// // cough is often present for a week or more before the case is notified
+    /// let disease_model =
+    ///     OnsetDisease::pertussis(rounded_poisson(10.).unwrap(), Gamma::new(2.0, 0.6).unwrap());
+    ///
+    /// let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+    /// let genome = SimpleGenome::<64>::default();
+    /// // Bordetella pertussis accumulates very few SNPs per year
+    /// let outbreak = simulate_outbreak(genome, &disease_model, 0.3 / 365.0, 100, &mut rng)
+    ///     .unwrap_or_else(|err| *err.outbreak);
+    /// assert!(outbreak.n_cases() > 0);
+    /// ```
+    pub fn pertussis(reporting_time: DRep, reproduction_number: DR) -> Self {
+        #[rustfmt::skip]
+        let infectiousness = vec![
+            0.07, 0.07, 0.07, 0.07, 0.07, 0.07, 0.07, 0.07, 0.07, 0.07, // catarrhal
+            0.05, 0.045, 0.04, 0.035, 0.03, 0.025, 0.02, 0.018, 0.015, 0.012, 0.01, // paroxysmal
+        ];
+        OnsetDisease {
+            incubation_time: rounded_poisson(8.5).unwrap(),
+            reporting_time,
+            reproduction_number,
+            infectiousness,
+            presymptomatic: 0,
+            fraction_symptomatic: 1.0,
+            asymptomatic_infectiousness: 0.0,
+        }
+    }
+}
+
+impl<DInc, DRep, DR> DiseaseModel for OnsetDisease<DInc, DRep, DR>
+where
+    DInc: Distribution<Time>,
+    DRep: Distribution<Time>,
+    DR: Distribution<f64>,
+{
+    type State = ();
+
+    fn generate_case<R: Rng>(&self, _state: &mut Self::State, mut rng: R) -> CaseHistory {
+        let symptomatic = rng.gen_bool(self.fraction_symptomatic);
+        let onset = self.incubation_time.sample(&mut rng);
+        let reported = symptomatic.then(|| onset + self.reporting_time.sample(&mut rng));
+
+        let mut r = self.reproduction_number.sample(&mut rng);
+        if !symptomatic {
+            r *= self.asymptomatic_infectiousness;
+        }
+
+        let skipped = self.presymptomatic.saturating_sub(onset) as usize;
+        let latent = onset.saturating_sub(self.presymptomatic) as usize;
+        let mut infectivity = vec![0.0; latent];
+        infectivity.extend(self.infectiousness.iter().skip(skipped).map(|x| x * r));
+
+        CaseHistory {
+            infectivity,
+            symptom_onset: symptomatic.then_some(onset),
+            reported,
+            sampled: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_distr::Uniform;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    #[test]
+    fn test_onset_alignment() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let onset = Uniform::new_inclusive(3, 3);
+        let model = OnsetDisease {
+            incubation_time: onset,
+            reporting_time: onset,
+            reproduction_number: Uniform::new_inclusive(2.0, 2.0),
+            infectiousness: vec![0.25, 0.5, 0.25],
+            presymptomatic: 1,
+            fraction_symptomatic: 1.0,
+            asymptomatic_infectiousness: 0.0,
+        };
+
+        let case = model.generate_singleton(&mut rng);
+        assert_eq!(case.infectivity, vec![0.0, 0.0, 0.5, 1.0, 0.5]);
+        assert_eq!(case.symptom_onset, Some(3));
+        assert_eq!(case.reported, Some(6));
+
+        let onset = Uniform::new_inclusive(0, 0);
+        let model = OnsetDisease {
+            incubation_time: onset,
+            reporting_time: onset,
+            ..model
+        };
+        let case = model.generate_singleton(&mut rng);
+        assert_eq!(case.infectivity, vec![1.0, 0.5]);
+    }
+
+    #[test]
+    fn test_published_profiles_normalised() {
+        let profiles = [
+            OnsetDisease::influenza((), ()).infectiousness,
+            OnsetDisease::measles((), ()).infectiousness,
+            OnsetDisease::mpox((), ()).infectiousness,
+            OnsetDisease::pertussis((), ()).infectiousness,
+        ];
+        for profile in profiles {
+            assert!((profile.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+}
@@ -47,14 +47,14 @@ where
         let onset = self.incubation_time.sample(&mut rng);
         let reported = onset + self.reporting_time.sample(&mut rng);
         let r = self.reproduction_number.sample(&mut rng);
-        let infect = std::iter::repeat(0.0)
-            .take(onset as usize)
-            .chain(self.infectiousness.iter().map(|x| x * r));
+        let mut infectivity = vec![0.0; onset as usize];
+        infectivity.extend(self.infectiousness.iter().map(|x| x * r));
 
         CaseHistory {
-            infectivity: infect.collect(),
+            infectivity,
             symptom_onset: Some(onset),
             reported: Some(reported),
+            sampled: None,
         }
     }
 
@@ -65,6 +65,28 @@ where
             infectivity: vec![],
             symptom_onset: Some(0),
             reported: Some(reported),
+            sampled: None,
         }
     }
 }
+
+/// Disease model shared by tests, with a mean incubation and reporting time of one time step and
+/// a gamma-distributed reproduction number.
+#[cfg(test)]
+pub(crate) fn test_disease(
+    shape: f64,
+    scale: f64,
+) -> SimpleDisease<
+    crate::simulate::RoundedPoisson,
+    crate::simulate::RoundedPoisson,
+    rand_distr::Gamma<f64>,
+> {
+    use crate::simulate::rounded_poisson;
+
+    SimpleDisease {
+        incubation_time: rounded_poisson(1.).unwrap(),
+        reporting_time: rounded_poisson(1.).unwrap(),
+        reproduction_number: rand_distr::Gamma::new(shape, scale).unwrap(),
+        infectiousness: vec![0.34, 0.33, 0.33],
+    }
+}
@@ -3,7 +3,12 @@ use rand_distr::{Distribution, Poisson};
 use std::io;
 
 use super::Time;
+pub mod nucleotide;
 pub mod simple;
+pub mod site_rates;
+pub mod sparse;
+pub mod substitution;
+pub mod within_host;
 
 /// Implemented by types that represent a genome sequence and a mutation model.
 pub trait Genome: Clone {
@@ -19,10 +24,31 @@ pub trait Genome: Clone {
     where
         Self: Sized,
     {
-        let n_mutations = mutations_by_time(generation_time, mutation_rate, &mut rng);
+        let n_mutations = poisson_mutations(f64::from(generation_time) * mutation_rate, &mut rng);
         self.mutate(n_mutations, rng)
     }
 
+    /// Induce mutations according to an elapsed interval of continuous time.
+    ///
+    /// This is the same as [`mutate_time`](Genome::mutate_time) for an interval that need not be
+    /// a whole number of time steps.
+    fn mutate_interval<R: Rng>(&self, interval: f64, mutation_rate: f64, mut rng: R) -> Self
+    where
+        Self: Sized,
+    {
+        let n_mutations = poisson_mutations(interval * mutation_rate, &mut rng);
+        self.mutate(n_mutations, rng)
+    }
+
+    /// Select the genetic material passed on to a newly infected case.
+    ///
+    /// This is applied after mutating the infector's genome up to the time of transmission. The
+    /// default passes on the whole genome unchanged, but genomes that represent a diverse
+    /// population can apply a transmission bottleneck.
+    fn transmit<R: Rng>(&self, _rng: R) -> Self {
+        self.clone()
+    }
+
     /// Measure SNP distance from another genome.
     fn snps(&self, other: &Self) -> u32;
 
@@ -30,8 +56,8 @@ pub trait Genome: Clone {
     fn write_nucleotides<W: io::Write>(&self, writer: W) -> io::Result<()>;
 }
 
-fn mutations_by_time<R: Rng>(generation_time: Time, mutation_rate: f64, mut rng: R) -> usize {
-    let lambda: f64 = f64::from(generation_time) * mutation_rate;
+/// Number of mutations when `lambda` are expected.
+fn poisson_mutations<R: Rng>(lambda: f64, mut rng: R) -> usize {
     if lambda <= 0.0 {
         0
     } else {
@@ -0,0 +1,488 @@
+use crate::genome::site_rates::{SiteRates, SiteRatesError};
+use crate::genome::substitution::{state_index, RateMatrix, SubstitutionModel, NUCLEOTIDES};
+use crate::genome::Genome;
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use rand_distr::Distribution;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Error constructing a genome from a nucleotide sequence.
+#[derive(Error, Debug)]
+pub enum SequenceError {
+    #[error("invalid nucleotide {:?} at position {position}", char::from(*.base))]
+    InvalidBase { position: usize, base: u8 },
+
+    #[error("no sequence found in FASTA input")]
+    MissingRecord,
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Whether a symbol is a nucleotide, IUPAC ambiguity code or gap.
+#[inline]
+fn is_iupac(base: u8) -> bool {
+    matches!(
+        base,
+        b'A' | b'C'
+            | b'G'
+            | b'T'
+            | b'R'
+            | b'Y'
+            | b'S'
+            | b'W'
+            | b'K'
+            | b'M'
+            | b'B'
+            | b'D'
+            | b'H'
+            | b'V'
+            | b'N'
+            | b'-'
+    )
+}
+
+/// Sampler for substitutions under a [`RateMatrix`].
+///
+/// This is shared between all genomes descending from the same ancestor.
+#[derive(Debug, Clone)]
+pub(crate) struct Mutator {
+    matrix: RateMatrix,
+    max_leaving_rate: f64,
+    targets: [Option<WeightedIndex<f64>>; 4],
+    sites: Option<SiteRates>,
+}
+
+impl Mutator {
+    pub(crate) fn new<M: SubstitutionModel>(model: &M) -> Self {
+        let matrix = model.rate_matrix();
+        let max_leaving_rate = (0..4).map(|i| matrix.leaving_rate(i)).fold(0.0, f64::max);
+        let targets = std::array::from_fn(|i| {
+            let weights = (0..4).map(|j| if i == j { 0.0 } else { matrix.rates()[i][j] });
+            WeightedIndex::new(weights).ok()
+        });
+        Mutator {
+            matrix,
+            max_leaving_rate,
+            targets,
+            sites: None,
+        }
+    }
+
+    /// Replace the site rates, which must cover `len` sites.
+    pub(crate) fn with_site_rates(
+        &self,
+        sites: SiteRates,
+        len: usize,
+    ) -> Result<Self, SiteRatesError> {
+        sites.check_len(len)?;
+        Ok(Mutator {
+            sites: Some(sites),
+            ..self.clone()
+        })
+    }
+
+    /// Choose a site to mutate and the new nucleotide at that site.
+    ///
+    /// Sites are selected in proportion to their site rate (uniformly by default) and then
+    /// accepted in proportion to the rate of leaving their current state. `base_at` looks up the
+    /// current nucleotide at a site.
+    ///
+    /// This must only be called when at least one site is [mutable](Mutator::is_mutable).
+    pub(crate) fn substitution<R, F>(&self, len: usize, base_at: F, mut rng: R) -> (usize, u8)
+    where
+        R: Rng,
+        F: Fn(usize) -> u8,
+    {
+        loop {
+            let pos = match &self.sites {
+                Some(sites) => sites.sample_site(&mut rng).unwrap(),
+                None => rng.gen_range(0..len),
+            };
+            let Some(state) = state_index(base_at(pos)) else {
+                continue;
+            };
+            if rng.gen::<f64>() * self.max_leaving_rate < self.matrix.leaving_rate(state) {
+                if let Some(target) = &self.targets[state] {
+                    return (pos, NUCLEOTIDES[target.sample(&mut rng)]);
+                }
+            }
+        }
+    }
+
+    /// Whether the site at `pos` can be substituted while it holds `base`.
+    ///
+    /// This requires a non-zero site rate and a non-zero rate of leaving the nucleotide.
+    pub(crate) fn is_mutable(&self, pos: usize, base: u8) -> bool {
+        let site_rate = self.sites.as_ref().map_or(1.0, |sites| sites.rates()[pos]);
+        site_rate > 0.0 && state_index(base).is_some_and(|i| self.matrix.leaving_rate(i) > 0.0)
+    }
+
+    /// Number of sites in `sequence` that are [mutable](Mutator::is_mutable).
+    pub(crate) fn mutable_sites(&self, sequence: &[u8]) -> usize {
+        if self.max_leaving_rate <= 0.0 {
+            return 0;
+        }
+        sequence
+            .iter()
+            .enumerate()
+            .filter(|(pos, base)| self.is_mutable(*pos, **base))
+            .count()
+    }
+
+    #[inline]
+    pub(crate) fn matrix(&self) -> &RateMatrix {
+        &self.matrix
+    }
+
+    #[cfg(feature = "serde")]
+    #[inline]
+    pub(crate) fn site_rates(&self) -> Option<&SiteRates> {
+        self.sites.as_ref()
+    }
+}
+
+/// Serialized form of the nucleotide genome types.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct NucleotideData {
+    pub(crate) model: RateMatrix,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) site_rates: Option<SiteRates>,
+    pub(crate) sequence: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) variants: Vec<(usize, char)>,
+}
+
+/// Representation of a genome with four nucleotide states.
+///
+/// Each site holds one of A, C, G or T. Sites may also hold an IUPAC ambiguity code (e.g. N) or a
+/// gap, typically because they were read from an incompletely resolved reference sequence. These
+/// sites are carried through unchanged and never mutate.
+///
+/// Mutations are substitutions drawn from a
+/// [`SubstitutionModel`]: a site is chosen in proportion to the rate of leaving its current
+/// state, and its new state is chosen in proportion to the corresponding entries of the rate
+/// matrix. The expected number of substitutions per unit time is therefore still given by the
+/// `mutation_rate` of the simulation.
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "NucleotideData", try_from = "NucleotideData")
+)]
+pub struct NucleotideGenome {
+    sequence: Vec<u8>,
+    mutator: Arc<Mutator>,
+}
+
+impl NucleotideGenome {
+    /// Create a genome from a nucleotide sequence.
+    ///
+    /// Lowercase nucleotides are accepted and converted to uppercase. IUPAC ambiguity codes and
+    /// gaps (`-`) are also accepted.
+    pub fn new<M, S>(sequence: S, model: &M) -> Result<Self, SequenceError>
+    where
+        M: SubstitutionModel,
+        S: Into<Vec<u8>>,
+    {
+        let mut sequence = sequence.into();
+        for (position, base) in sequence.iter_mut().enumerate() {
+            base.make_ascii_uppercase();
+            if !is_iupac(*base) {
+                return Err(SequenceError::InvalidBase {
+                    position,
+                    base: *base,
+                });
+            }
+        }
+        Ok(NucleotideGenome {
+            sequence,
+            mutator: Arc::new(Mutator::new(model)),
+        })
+    }
+
+    /// Create a genome from the first record of a FASTA file.
+    ///
+    /// This is useful to start a simulation from a real reference genome. The sequence may be
+    /// wrapped over any number of lines. Any further records in the input are ignored.
+    ///
+    /// ```no_run
+    /// use obsim::nucleotide::{NucleotideGenome, K80};
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    ///
+    /// let reader = BufReader::new(File::open("MN908947.3.fasta")?);
+    /// let genome = NucleotideGenome::from_fasta(reader, &K80::new(3.0)?)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_fasta<M, R>(reader: R, model: &M) -> Result<Self, SequenceError>
+    where
+        M: SubstitutionModel,
+        R: BufRead,
+    {
+        let mut sequence = Vec::new();
+        let mut in_record = false;
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim_end();
+            if line.starts_with('>') {
+                if in_record {
+                    break;
+                }
+                in_record = true;
+            } else if in_record {
+                sequence.extend_from_slice(line.as_bytes());
+            } else if !line.is_empty() {
+                return Err(SequenceError::MissingRecord);
+            }
+        }
+
+        if !in_record {
+            return Err(SequenceError::MissingRecord);
+        }
+        Self::new(sequence, model)
+    }
+
+    /// Create a random genome of length `len` with the equilibrium base frequencies of `model`.
+    pub fn random<M: SubstitutionModel, R: Rng>(len: usize, model: &M, mut rng: R) -> Self {
+        let mutator = Mutator::new(model);
+        let bases = WeightedIndex::new(mutator.matrix().frequencies()).unwrap();
+        let sequence = (0..len)
+            .map(|_| NUCLEOTIDES[bases.sample(&mut rng)])
+            .collect();
+        NucleotideGenome {
+            sequence,
+            mutator: Arc::new(mutator),
+        }
+    }
+
+    /// The nucleotide sequence.
+    #[inline]
+    pub fn sequence(&self) -> &[u8] {
+        &self.sequence
+    }
+
+    /// Number of sites in the genome.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sequence.len()
+    }
+
+    /// Whether the genome has no sites.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sequence.is_empty()
+    }
+
+    /// Choose the sites that mutate in proportion to `rates`.
+    ///
+    /// By default all sites are equally likely to mutate. Fails if `rates` does not have one rate
+    /// per site.
+    pub fn with_site_rates(self, rates: SiteRates) -> Result<Self, SiteRatesError> {
+        let mutator = self.mutator.with_site_rates(rates, self.len())?;
+        Ok(NucleotideGenome {
+            sequence: self.sequence,
+            mutator: Arc::new(mutator),
+        })
+    }
+
+    pub(crate) fn into_parts(self) -> (Vec<u8>, Arc<Mutator>) {
+        (self.sequence, self.mutator)
+    }
+}
+
+impl Genome for NucleotideGenome {
+    /// Apply exactly `n_mutations` substitutions.
+    ///
+    /// Sites are chosen with replacement, so multiple hits and back-mutations are possible.
+    fn mutate<R: Rng>(&self, n_mutations: usize, mut rng: R) -> Self {
+        let mut new_genome = self.clone();
+        let mut n_mutable = self.mutator.mutable_sites(&self.sequence);
+        for _ in 0..n_mutations {
+            // substitutions can leave every site in a state that can't be left
+            if n_mutable == 0 {
+                break;
+            }
+            let seq = &new_genome.sequence;
+            let (pos, base) = self.mutator.substitution(seq.len(), |i| seq[i], &mut rng);
+            new_genome.sequence[pos] = base;
+            if !self.mutator.is_mutable(pos, base) {
+                n_mutable -= 1;
+            }
+        }
+        new_genome
+    }
+
+    /// Counts the sites where the nucleotides differ.
+    fn snps(&self, other: &Self) -> u32 {
+        self.sequence
+            .iter()
+            .zip(&other.sequence)
+            .filter(|(a, b)| a != b)
+            .count()
+            .try_into()
+            .unwrap()
+    }
+
+    fn write_nucleotides<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.sequence)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<NucleotideGenome> for NucleotideData {
+    fn from(genome: NucleotideGenome) -> Self {
+        NucleotideData {
+            model: *genome.mutator.matrix(),
+            site_rates: genome.mutator.site_rates().cloned(),
+            sequence: String::from_utf8_lossy(&genome.sequence).into_owned(),
+            variants: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<NucleotideData> for NucleotideGenome {
+    type Error = String;
+
+    fn try_from(data: NucleotideData) -> Result<Self, Self::Error> {
+        let genome =
+            NucleotideGenome::new(data.sequence, &data.model).map_err(|e| e.to_string())?;
+        match data.site_rates {
+            Some(rates) => genome.with_site_rates(rates).map_err(|e| e.to_string()),
+            None => Ok(genome),
+        }
+    }
+}
+
+impl PartialEq for NucleotideGenome {
+    fn eq(&self, other: &Self) -> bool {
+        self.sequence == other.sequence
+    }
+}
+
+impl Eq for NucleotideGenome {}
+
+impl fmt::Debug for NucleotideGenome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "NucleotideGenome({})",
+            String::from_utf8_lossy(&self.sequence)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genome::site_rates::SiteRates;
+    use crate::genome::sparse::SparseGenome;
+    use crate::genome::substitution::{Gtr, Jc69, K80};
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    #[test]
+    fn test_invalid_sequence() {
+        assert!(NucleotideGenome::new("acgtACGT", &Jc69).is_ok());
+        assert!(matches!(
+            NucleotideGenome::new("ACGU", &Jc69),
+            Err(SequenceError::InvalidBase {
+                position: 3,
+                base: b'U'
+            })
+        ));
+    }
+
+    #[test]
+    fn test_from_fasta() {
+        let fasta = ">ref description\nACGTN\nacg\n>other\nTTTT\n";
+        let genome = NucleotideGenome::from_fasta(fasta.as_bytes(), &Jc69).unwrap();
+        assert_eq!(genome.sequence(), b"ACGTNACG");
+
+        assert!(matches!(
+            NucleotideGenome::from_fasta("ACGT\n".as_bytes(), &Jc69),
+            Err(SequenceError::MissingRecord)
+        ));
+    }
+
+    #[test]
+    fn test_ambiguous_sites_fixed() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let genome = NucleotideGenome::new("NNNNANNNN-", &Jc69).unwrap();
+        let child = genome.mutate(3, &mut rng);
+        assert_eq!(child.sequence()[..4], *b"NNNN");
+        assert_eq!(child.sequence()[5..], *b"NNNN-");
+
+        let genome = NucleotideGenome::new("NNNN", &Jc69).unwrap();
+        assert_eq!(genome.mutate(3, &mut rng), genome);
+    }
+
+    #[test]
+    fn test_fixed_states() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let only_ac = Gtr::new([1.0, 0.0, 0.0, 0.0, 0.0, 0.0], [0.25; 4]).unwrap();
+        let genome = NucleotideGenome::new("GGGG", &only_ac).unwrap();
+        assert_eq!(genome.mutate(3, &mut rng), genome);
+
+        // A can only become G, which can never change again
+        let absorbing = Gtr::new([0.0, 1.0, 0.0, 0.0, 1.0, 0.0], [0.0, 0.25, 0.5, 0.25]).unwrap();
+        let genome = NucleotideGenome::new("AANA", &absorbing).unwrap();
+        assert_eq!(genome.mutate(10, &mut rng).sequence(), b"GGNG");
+        let sparse = SparseGenome::from(genome).mutate(2, &mut rng);
+        assert_eq!(sparse.mutate(10, &mut rng).sequence(), b"GGNG");
+    }
+
+    #[test]
+    fn test_site_rates() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let mut rates = vec![0.0; 100];
+        rates[10] = 1.0;
+        rates[20] = 3.0;
+        let genome = NucleotideGenome::random(100, &Jc69, &mut rng)
+            .with_site_rates(SiteRates::new(rates).unwrap())
+            .unwrap();
+        let child = genome.mutate(50, &mut rng);
+        for (i, (a, b)) in genome.sequence().iter().zip(child.sequence()).enumerate() {
+            assert!(a == b || i == 10 || i == 20);
+        }
+
+        let short = SiteRates::new(vec![1.0; 10]).unwrap();
+        assert!(genome.with_site_rates(short).is_err());
+    }
+
+    #[test]
+    fn test_distance() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let genome = NucleotideGenome::random(1000, &Jc69, &mut rng);
+        let child = genome.mutate(5, &mut rng);
+        assert_eq!(genome.snps(&child), 5);
+        assert_eq!(child.snps(&genome), 5);
+    }
+
+    #[test]
+    fn test_transition_bias() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let model = K80::new(20.0).unwrap();
+        let genome = NucleotideGenome::random(10000, &model, &mut rng);
+        let child = genome.mutate(200, &mut rng);
+
+        let transitions = genome
+            .sequence()
+            .iter()
+            .zip(child.sequence())
+            .filter(|(a, b)| a != b)
+            .filter(|(a, b)| {
+                matches!(
+                    (a, b),
+                    (b'A', b'G') | (b'G', b'A') | (b'C', b'T') | (b'T', b'C')
+                )
+            })
+            .count();
+        assert!(transitions as u32 > genome.snps(&child) * 3 / 4);
+    }
+}
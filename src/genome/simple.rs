@@ -1,75 +1,367 @@
+use crate::genome::site_rates::{SiteRates, SiteRatesError};
 use crate::genome::Genome;
 use bitvec::prelude::*;
-use rand::seq::index;
 use rand::Rng;
-use std::convert::TryInto;
 use std::fmt;
 use std::io;
+use std::sync::Arc;
 
 type GenomeStorage = BitBox<usize, Lsb0>;
 
 /// Representation of a genome.
 ///
 /// Internally this is represented as an array of binary bits. Mutation is modelled by
-/// selecting bits uniformly at random and flipping them.
+/// selecting bits uniformly at random (with replacement) and flipping them. Use
+/// [`with_site_rates`](SimpleGenome::with_site_rates) to select the bits in proportion to
+/// [`SiteRates`] instead.
 ///
-/// This model enables efficient operations and compact storage.
-#[derive(PartialEq, Eq, Clone)]
-pub struct SimpleGenome<const BP: usize>(GenomeStorage);
+/// This model enables efficient operations and compact storage. The length is fixed at compile
+/// time; see [`DynSimpleGenome`] to choose it at runtime instead.
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SimpleData", try_from = "SimpleData")
+)]
+pub struct SimpleGenome<const BP: usize> {
+    bits: GenomeStorage,
+    sites: Option<Arc<SiteRates>>,
+}
 
 impl<const BP: usize> Default for SimpleGenome<BP> {
     fn default() -> Self {
-        SimpleGenome(bitbox![usize, Lsb0; 0; BP])
+        SimpleGenome {
+            bits: bitbox![usize, Lsb0; 0; BP],
+            sites: None,
+        }
+    }
+}
+
+impl<const BP: usize> SimpleGenome<BP> {
+    /// Choose the bits that flip in proportion to `rates`.
+    ///
+    /// The rates are shared with all genomes derived from this one. Fails if `rates` does not
+    /// have `BP` sites.
+    pub fn with_site_rates(self, rates: SiteRates) -> Result<Self, SiteRatesError> {
+        rates.check_len(BP)?;
+        Ok(SimpleGenome {
+            sites: Some(Arc::new(rates)),
+            ..self
+        })
     }
 }
 
 impl<const BP: usize> Genome for SimpleGenome<BP> {
-    /// Flip exactly `n_mutations` bits chosen at random.
+    /// Flip `n_mutations` bits chosen at random with replacement.
     ///
-    /// Panics if the requested number of mutations is greater than the fixed width of the
-    /// representation.
-    fn mutate<R: Rng>(&self, n_mutations: usize, mut rng: R) -> Self {
-        assert!(
-            n_mutations <= BP,
-            "Requested number of mutations ({}) exceeds width of genome representation ({})",
-            n_mutations,
-            BP
-        );
-        let mut new_genome = self.0.clone();
-        for pos in index::sample(&mut rng, BP, n_mutations) {
-            let val = !new_genome.get(pos).unwrap();
-            new_genome.set(pos, val);
+    /// The same bit can be chosen more than once, and a bit that is hit twice flips back, so the two
+    /// hits cancel out. This means that the SNP distance to the original genome can be smaller than
+    /// `n_mutations`, and saturates for long divergence times.
+    fn mutate<R: Rng>(&self, n_mutations: usize, rng: R) -> Self {
+        SimpleGenome {
+            bits: flip_bits(&self.bits, self.sites.as_deref(), n_mutations, rng),
+            sites: self.sites.clone(),
         }
-        Self(new_genome)
     }
 
     /// Counts the bitwise differences between the genome representations.
     fn snps(&self, other: &Self) -> u32 {
-        (self.0.clone() ^ other.0.clone())
-            .count_ones()
-            .try_into()
-            .unwrap()
+        bit_distance(&self.bits, &other.bits)
     }
 
     /// Relabels 1 and 0 as A and C respectively.
-    fn write_nucleotides<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
-        for base in self.0.as_bitslice() {
-            write!(writer, "{}", if *base { 'A' } else { 'C' })?;
-        }
-        Ok(())
+    fn write_nucleotides<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        write_bits(&self.bits, writer)
     }
 }
 
+impl<const BP: usize> PartialEq for SimpleGenome<BP> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits == other.bits
+    }
+}
+
+impl<const BP: usize> Eq for SimpleGenome<BP> {}
+
 impl<const BP: usize> fmt::Debug for SimpleGenome<BP> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "SimpleGenome(")?;
-        for base in self.0.as_bitslice() {
-            write!(f, "{}", if *base { 'A' } else { 'C' })?;
+        debug_bits(&self.bits, f)?;
+        write!(f, ")")
+    }
+}
+
+/// Representation of a genome with a length chosen at runtime.
+///
+/// This behaves identically to [`SimpleGenome`] and uses the same compact storage, but the
+/// number of sites is given to [`new`](DynSimpleGenome::new) rather than as a type parameter.
+/// Genomes of different lengths should not be compared.
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SimpleData", try_from = "SimpleData")
+)]
+pub struct DynSimpleGenome {
+    bits: GenomeStorage,
+    sites: Option<Arc<SiteRates>>,
+}
+
+impl DynSimpleGenome {
+    /// Create a genome with `len` sites that are all in the same state.
+    pub fn new(len: usize) -> Self {
+        DynSimpleGenome {
+            bits: bitbox![usize, Lsb0; 0; len],
+            sites: None,
+        }
+    }
+
+    /// Choose the bits that flip in proportion to `rates`.
+    ///
+    /// See [`SimpleGenome::with_site_rates`].
+    pub fn with_site_rates(self, rates: SiteRates) -> Result<Self, SiteRatesError> {
+        rates.check_len(self.len())?;
+        Ok(DynSimpleGenome {
+            sites: Some(Arc::new(rates)),
+            ..self
+        })
+    }
+
+    /// Number of sites in the genome.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Whether the genome has no sites.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+}
+
+impl Genome for DynSimpleGenome {
+    /// Flip `n_mutations` bits chosen at random with replacement.
+    ///
+    /// See [`SimpleGenome`] for details.
+    fn mutate<R: Rng>(&self, n_mutations: usize, rng: R) -> Self {
+        DynSimpleGenome {
+            bits: flip_bits(&self.bits, self.sites.as_deref(), n_mutations, rng),
+            sites: self.sites.clone(),
         }
+    }
+
+    /// Counts the bitwise differences between the genome representations.
+    fn snps(&self, other: &Self) -> u32 {
+        bit_distance(&self.bits, &other.bits)
+    }
+
+    /// Relabels 1 and 0 as A and C respectively.
+    fn write_nucleotides<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        write_bits(&self.bits, writer)
+    }
+}
+
+impl PartialEq for DynSimpleGenome {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits == other.bits
+    }
+}
+
+impl Eq for DynSimpleGenome {}
+
+impl fmt::Debug for DynSimpleGenome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DynSimpleGenome(")?;
+        debug_bits(&self.bits, f)?;
         write!(f, ")")
     }
 }
 
+impl<const BP: usize> From<SimpleGenome<BP>> for String {
+    /// Relabels 1 and 0 as A and C respectively.
+    fn from(genome: SimpleGenome<BP>) -> Self {
+        bits_to_string(&genome.bits)
+    }
+}
+
+impl<const BP: usize> TryFrom<String> for SimpleGenome<BP> {
+    type Error = String;
+
+    /// Parses a string of A and C, which must have length `BP`.
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.len() != BP {
+            return Err(format!(
+                "expected a genome of length {} but found {}",
+                BP,
+                value.len()
+            ));
+        }
+        let bits = string_to_bits(&value)?;
+        Ok(SimpleGenome { bits, sites: None })
+    }
+}
+
+impl From<DynSimpleGenome> for String {
+    /// Relabels 1 and 0 as A and C respectively.
+    fn from(genome: DynSimpleGenome) -> Self {
+        bits_to_string(&genome.bits)
+    }
+}
+
+impl TryFrom<String> for DynSimpleGenome {
+    type Error = String;
+
+    /// Parses a string of A and C.
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let bits = string_to_bits(&value)?;
+        Ok(DynSimpleGenome { bits, sites: None })
+    }
+}
+
+/// Serialized form of the simple genome types.
+///
+/// Genomes without site rates are just their string of A and C.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub(crate) enum SimpleData {
+    Sequence(String),
+    WithSiteRates {
+        sequence: String,
+        site_rates: SiteRates,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl SimpleData {
+    fn new(sequence: String, sites: Option<Arc<SiteRates>>) -> Self {
+        match sites {
+            Some(sites) => SimpleData::WithSiteRates {
+                sequence,
+                site_rates: (*sites).clone(),
+            },
+            None => SimpleData::Sequence(sequence),
+        }
+    }
+
+    fn into_parts(self) -> (String, Option<SiteRates>) {
+        match self {
+            SimpleData::Sequence(sequence) => (sequence, None),
+            SimpleData::WithSiteRates {
+                sequence,
+                site_rates,
+            } => (sequence, Some(site_rates)),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const BP: usize> From<SimpleGenome<BP>> for SimpleData {
+    fn from(genome: SimpleGenome<BP>) -> Self {
+        let sites = genome.sites.clone();
+        SimpleData::new(genome.into(), sites)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const BP: usize> TryFrom<SimpleData> for SimpleGenome<BP> {
+    type Error = String;
+
+    fn try_from(value: SimpleData) -> Result<Self, Self::Error> {
+        let (sequence, sites) = value.into_parts();
+        let genome = SimpleGenome::try_from(sequence)?;
+        match sites {
+            Some(sites) => genome.with_site_rates(sites).map_err(|e| e.to_string()),
+            None => Ok(genome),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<DynSimpleGenome> for SimpleData {
+    fn from(genome: DynSimpleGenome) -> Self {
+        let sites = genome.sites.clone();
+        SimpleData::new(genome.into(), sites)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SimpleData> for DynSimpleGenome {
+    type Error = String;
+
+    fn try_from(value: SimpleData) -> Result<Self, Self::Error> {
+        let (sequence, sites) = value.into_parts();
+        let genome = DynSimpleGenome::try_from(sequence)?;
+        match sites {
+            Some(sites) => genome.with_site_rates(sites).map_err(|e| e.to_string()),
+            None => Ok(genome),
+        }
+    }
+}
+
+fn bits_to_string(genome: &GenomeStorage) -> String {
+    genome.iter().map(|x| if *x { 'A' } else { 'C' }).collect()
+}
+
+fn string_to_bits(value: &str) -> Result<GenomeStorage, String> {
+    value
+        .chars()
+        .map(|x| match x {
+            'A' => Ok(true),
+            'C' => Ok(false),
+            _ => Err(format!("invalid base {:?} in simple genome", x)),
+        })
+        .collect()
+}
+
+fn flip_bits<R: Rng>(
+    genome: &GenomeStorage,
+    sites: Option<&SiteRates>,
+    n_mutations: usize,
+    mut rng: R,
+) -> GenomeStorage {
+    let mut new_genome = genome.clone();
+    if new_genome.is_empty() {
+        return new_genome;
+    }
+    for _ in 0..n_mutations {
+        let pos = match sites {
+            Some(sites) => match sites.sample_site(&mut rng) {
+                Some(pos) => pos,
+                None => break,
+            },
+            None => rng.gen_range(0..new_genome.len()),
+        };
+        let val = !new_genome[pos];
+        new_genome.set(pos, val);
+    }
+    new_genome
+}
+
+// loading each chunk only reads the bits within the genome, whatever is stored past its end
+fn bit_distance(a: &GenomeStorage, b: &GenomeStorage) -> u32 {
+    let word = usize::BITS as usize;
+    a.chunks(word)
+        .zip(b.chunks(word))
+        .map(|(a, b)| (a.load::<usize>() ^ b.load::<usize>()).count_ones())
+        .sum()
+}
+
+fn write_bits<W: io::Write>(genome: &GenomeStorage, mut writer: W) -> io::Result<()> {
+    for base in genome.as_bitslice() {
+        write!(writer, "{}", if *base { 'A' } else { 'C' })?;
+    }
+    Ok(())
+}
+
+fn debug_bits(genome: &GenomeStorage, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for base in genome.as_bitslice() {
+        write!(f, "{}", if *base { 'A' } else { 'C' })?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,17 +377,121 @@ mod tests {
 
     #[test]
     fn test_mutation() {
-        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324 as u64);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
         let genome = SimpleGenome::<64>::default();
         assert_ne!(genome, genome.mutate(4, &mut rng));
     }
 
     #[test]
     fn test_distance() {
-        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324 as u64);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
         let genome = SimpleGenome::<64>::default();
         let child = genome.mutate(5, &mut rng);
         assert_eq!(genome.snps(&child), 5);
         assert_eq!(child.snps(&genome), 5);
+
+        // only the sites hit an odd number of times differ
+        let mut replay = rng.clone();
+        let child = genome.mutate(40, &mut rng);
+        let mut hits = [0; 64];
+        for _ in 0..40 {
+            hits[replay.gen_range(0..64)] += 1;
+        }
+        let odd = hits.iter().filter(|x| **x % 2 == 1).count() as u32;
+        assert!(odd < 40);
+        assert_eq!(genome.snps(&child), odd);
+    }
+
+    #[test]
+    fn test_back_mutation() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let genome = SimpleGenome::<1>::default();
+        assert_eq!(genome.snps(&genome.mutate(1, &mut rng)), 1);
+        assert_eq!(genome.mutate(2, &mut rng), genome);
+        assert_eq!(genome.mutate(7, &mut rng).mutate(3, &mut rng), genome);
+    }
+
+    #[test]
+    fn test_saturation() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let genome = SimpleGenome::<64>::default();
+        let child = genome.mutate(1000, &mut rng);
+        assert!(genome.snps(&child) <= 64);
+        assert_eq!(genome.mutate(0, &mut rng), genome);
+    }
+
+    #[test]
+    fn test_site_rates() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let mut rates = vec![0.0; 64];
+        rates[10] = 1.0;
+        rates[20] = 3.0;
+        let rates = SiteRates::new(rates).unwrap();
+        let genome = SimpleGenome::<64>::default()
+            .with_site_rates(rates.clone())
+            .unwrap();
+        let child = genome.mutate(51, &mut rng);
+        assert!(genome.snps(&child) >= 1);
+        let mut written = Vec::new();
+        child.write_nucleotides(&mut written).unwrap();
+        for (i, base) in written.iter().enumerate() {
+            assert!(*base == b'C' || i == 10 || i == 20);
+        }
+
+        let genome = DynSimpleGenome::new(64).with_site_rates(rates).unwrap();
+        assert!(genome.mutate(51, &mut rng).snps(&genome) >= 1);
+        let short = SiteRates::new(vec![1.0; 10]).unwrap();
+        assert!(genome.with_site_rates(short).is_err());
+
+        let invariant = SiteRates::new(vec![0.0; 64]).unwrap();
+        let genome = SimpleGenome::<64>::default()
+            .with_site_rates(invariant)
+            .unwrap();
+        assert_eq!(genome.mutate(5, &mut rng), genome);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_site_rates() {
+        let genome = DynSimpleGenome::new(4);
+        assert_eq!(serde_json::to_string(&genome).unwrap(), "\"CCCC\"");
+
+        let rates = SiteRates::new(vec![1.0, 0.0, 0.0, 2.0]).unwrap();
+        let genome = genome.with_site_rates(rates).unwrap();
+        let json = serde_json::to_string(&genome).unwrap();
+        let restored: DynSimpleGenome = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.sites.unwrap().rates(), &[1.0, 0.0, 0.0, 2.0]);
+        let invalid = json.replace("CCCC", "CCC");
+        assert!(serde_json::from_str::<DynSimpleGenome>(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_distance_ignores_unused_bits() {
+        let mut a = bitvec![usize, Lsb0; 0; 2 * usize::BITS as usize];
+        let mut b = a.clone();
+        a.set(3, true);
+        a.set(70, true);
+        b[71..].fill(true);
+        a.truncate(71);
+        b.truncate(71);
+        let (a, b) = (a.into_boxed_bitslice(), b.into_boxed_bitslice());
+        assert_eq!(bit_distance(&a, &b), 2);
+
+        let genome = DynSimpleGenome::new(71);
+        let child = genome.mutate(1, Xoshiro256PlusPlus::seed_from_u64(89324_u64));
+        assert_eq!(genome.snps(&child), 1);
+    }
+
+    #[test]
+    fn test_dynamic_length() {
+        let mut rng1 = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let mut rng2 = rng1.clone();
+        let genome = DynSimpleGenome::new(64);
+        let child = genome.mutate(5, &mut rng1);
+        assert_eq!(genome.len(), 64);
+        assert_eq!(genome.snps(&child), 5);
+
+        let fixed = SimpleGenome::<64>::default().mutate(5, &mut rng2);
+        assert_eq!(format!("{:?}", child)[3..], format!("{:?}", fixed)[..]);
     }
 }
@@ -0,0 +1,226 @@
+//! Variation of mutation rates among sites.
+
+use rand::distributions::WeightedIndex;
+use rand::seq::index;
+use rand::Rng;
+use rand_distr::{Distribution, Gamma};
+use std::io::{self, BufRead};
+use thiserror::Error;
+
+/// Error constructing [`SiteRates`].
+#[derive(Error, Debug)]
+pub enum SiteRatesError {
+    #[error("site rates must be finite and non-negative, with a finite sum")]
+    InvalidRate,
+
+    #[error("gamma shape parameter must be finite and positive")]
+    InvalidShape,
+
+    #[error("proportion of invariant sites must be at least zero and less than one")]
+    InvalidProportion,
+
+    #[error("expected rates for {expected} sites but found {found}")]
+    LengthMismatch { expected: usize, found: usize },
+
+    #[error("could not parse site rate {value:?} on line {line}")]
+    Parse { line: usize, value: String },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Relative mutation rates of each site in a genome.
+///
+/// Genomes that use these rates choose which sites mutate in proportion to the rate of each site,
+/// so the rates only need to be known up to a constant factor. Sites with zero rate are invariant
+/// and never mutate.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "Vec<f64>", try_from = "Vec<f64>")
+)]
+pub struct SiteRates {
+    rates: Vec<f64>,
+    sampler: Option<WeightedIndex<f64>>,
+}
+
+impl SiteRates {
+    /// Use the given rate for each site.
+    pub fn new(rates: Vec<f64>) -> Result<Self, SiteRatesError> {
+        if rates.iter().any(|x| !x.is_finite() || *x < 0.0) {
+            return Err(SiteRatesError::InvalidRate);
+        }
+        if !rates.iter().sum::<f64>().is_finite() {
+            return Err(SiteRatesError::InvalidRate);
+        }
+        let sampler = WeightedIndex::new(&rates).ok();
+        Ok(SiteRates { rates, sampler })
+    }
+
+    /// Draw rates for `len` sites from a gamma distribution with mean one.
+    ///
+    /// Smaller values of `shape` give more extreme rate variation, with most mutations falling
+    /// into a few hotspots.
+    pub fn gamma<R: Rng>(len: usize, shape: f64, mut rng: R) -> Result<Self, SiteRatesError> {
+        if !shape.is_finite() || shape <= 0.0 {
+            return Err(SiteRatesError::InvalidShape);
+        }
+        let dist = Gamma::new(shape, 1.0 / shape).map_err(|_| SiteRatesError::InvalidShape)?;
+        Self::new(dist.sample_iter(&mut rng).take(len).collect())
+    }
+
+    /// Read one rate per site from a text file.
+    ///
+    /// Rates are separated by any whitespace. Anything following a `#` on a line is ignored.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, SiteRatesError> {
+        let mut rates = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let content = line.split('#').next().unwrap_or_default();
+            for value in content.split_whitespace() {
+                let rate = value.parse().map_err(|_| SiteRatesError::Parse {
+                    line: i + 1,
+                    value: value.to_owned(),
+                })?;
+                rates.push(rate);
+            }
+        }
+        Self::new(rates)
+    }
+
+    /// Make a random `proportion` of the sites invariant.
+    ///
+    /// The rates of the remaining sites are scaled up so that the mean rate is unchanged, unless
+    /// every remaining site is already invariant.
+    pub fn with_invariant<R: Rng>(
+        self,
+        proportion: f64,
+        mut rng: R,
+    ) -> Result<Self, SiteRatesError> {
+        if !(0.0..1.0).contains(&proportion) {
+            return Err(SiteRatesError::InvalidProportion);
+        }
+        let mut rates = self.rates;
+        let total: f64 = rates.iter().sum();
+        let n_invariant = (proportion * rates.len() as f64).round() as usize;
+        for pos in index::sample(&mut rng, rates.len(), n_invariant) {
+            rates[pos] = 0.0;
+        }
+        let variable: f64 = rates.iter().sum();
+        if variable > 0.0 {
+            for rate in &mut rates {
+                *rate *= total / variable;
+            }
+        }
+        Self::new(rates)
+    }
+
+    /// The relative rate of each site.
+    #[inline]
+    pub fn rates(&self) -> &[f64] {
+        &self.rates
+    }
+
+    /// Number of sites.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.rates.len()
+    }
+
+    /// Whether there are no sites.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+
+    pub(crate) fn check_len(&self, expected: usize) -> Result<(), SiteRatesError> {
+        if self.len() != expected {
+            return Err(SiteRatesError::LengthMismatch {
+                expected,
+                found: self.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Choose a site in proportion to its rate, or `None` if all sites are invariant.
+    pub(crate) fn sample_site<R: Rng>(&self, mut rng: R) -> Option<usize> {
+        self.sampler.as_ref().map(|x| x.sample(&mut rng))
+    }
+}
+
+impl From<SiteRates> for Vec<f64> {
+    fn from(rates: SiteRates) -> Self {
+        rates.rates
+    }
+}
+
+impl TryFrom<Vec<f64>> for SiteRates {
+    type Error = SiteRatesError;
+
+    fn try_from(rates: Vec<f64>) -> Result<Self, Self::Error> {
+        SiteRates::new(rates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    #[test]
+    fn test_from_reader() {
+        let input = "# relative rates\n1.0 2.5\n0 # conserved\n\n3\n";
+        let rates = SiteRates::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(rates.rates(), &[1.0, 2.5, 0.0, 3.0]);
+
+        assert!(matches!(
+            SiteRates::from_reader("1.0\nfast\n".as_bytes()),
+            Err(SiteRatesError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_invariant() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let rates = SiteRates::new(vec![1.0; 100])
+            .unwrap()
+            .with_invariant(0.2, &mut rng)
+            .unwrap();
+        assert_eq!(rates.rates().iter().filter(|x| **x == 0.0).count(), 20);
+        assert!((rates.rates().iter().sum::<f64>() - 100.0).abs() < 1e-9);
+
+        // 3 of 10 sites are made invariant, rather than the requested 2.5
+        let rounded = SiteRates::new(vec![1.0; 10])
+            .unwrap()
+            .with_invariant(0.25, &mut rng)
+            .unwrap();
+        assert_eq!(rounded.rates().iter().filter(|x| **x == 0.0).count(), 3);
+        assert!((rounded.rates().iter().sum::<f64>() - 10.0).abs() < 1e-9);
+
+        let gamma = SiteRates::gamma(50, 0.5, &mut rng).unwrap();
+        let total: f64 = gamma.rates().iter().sum();
+        let gamma = gamma.with_invariant(0.3, &mut rng).unwrap();
+        assert!((gamma.rates().iter().sum::<f64>() - total).abs() < 1e-9);
+
+        for _ in 0..100 {
+            let site = rates.sample_site(&mut rng).unwrap();
+            assert!(rates.rates()[site] > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_overflowing_total() {
+        let rates = SiteRates::new(vec![f64::MAX, f64::MAX]);
+        assert!(matches!(rates, Err(SiteRatesError::InvalidRate)));
+    }
+
+    #[test]
+    fn test_all_invariant() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let rates = SiteRates::new(vec![0.0; 10]).unwrap();
+        assert_eq!(rates.sample_site(&mut rng), None);
+    }
+}
@@ -0,0 +1,454 @@
+#[cfg(feature = "serde")]
+use crate::genome::nucleotide::NucleotideData;
+use crate::genome::nucleotide::{Mutator, NucleotideGenome};
+use crate::genome::site_rates::{SiteRates, SiteRatesError};
+use crate::genome::Genome;
+use rand::Rng;
+#[cfg(feature = "serde")]
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+/// Representation of a genome as a list of mutations from a shared reference.
+///
+/// This has the same mutation model as [`NucleotideGenome`], but only the sites that differ from
+/// the reference sequence are stored. The reference itself is shared between all genomes derived
+/// from it. This makes it practical to simulate large outbreaks of pathogens with long genomes,
+/// where each case differs from the reference at only a handful of sites.
+///
+/// SNP distances are computed from the two mutation lists, so they are only efficient for genomes
+/// that share the same reference.
+///
+/// When an [`Outbreak`](crate::Outbreak) is serialized, each reference sequence is written in full
+/// only with the first genome that uses it, and later genomes refer back to it by number, so
+/// they only store their mutation list. Deserialized genomes share their reference again. A
+/// genome serialized on its own always includes its reference sequence.
+#[derive(Clone)]
+pub struct SparseGenome {
+    lineage: Arc<Lineage>,
+    variants: Vec<(usize, u8)>,
+}
+
+/// Reference sequence and mutation model shared by all genomes derived from one another.
+struct Lineage {
+    reference: Arc<[u8]>,
+    mutator: Mutator,
+    /// Number of mutable sites in the reference, so that mutations don't need to scan it.
+    n_mutable: usize,
+}
+
+impl Lineage {
+    fn new(reference: Arc<[u8]>, mutator: Mutator) -> Arc<Self> {
+        let n_mutable = mutator.mutable_sites(&reference);
+        Arc::new(Lineage {
+            reference,
+            mutator,
+            n_mutable,
+        })
+    }
+}
+
+impl SparseGenome {
+    /// Positions and nucleotides where this genome differs from its reference, sorted by
+    /// position.
+    #[inline]
+    pub fn variants(&self) -> &[(usize, u8)] {
+        &self.variants
+    }
+
+    /// The reference sequence shared by this genome's lineage.
+    #[inline]
+    pub fn reference(&self) -> &[u8] {
+        &self.lineage.reference
+    }
+
+    /// Reconstruct the full nucleotide sequence.
+    pub fn sequence(&self) -> Vec<u8> {
+        let mut sequence = self.lineage.reference.to_vec();
+        for &(pos, base) in &self.variants {
+            sequence[pos] = base;
+        }
+        sequence
+    }
+
+    /// Number of sites in the genome.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lineage.reference.len()
+    }
+
+    /// Whether the genome has no sites.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lineage.reference.is_empty()
+    }
+
+    /// Choose the sites that mutate in proportion to `rates`.
+    ///
+    /// The rates are shared with all genomes derived from this one. Fails if `rates` does not
+    /// have one rate per site.
+    pub fn with_site_rates(self, rates: SiteRates) -> Result<Self, SiteRatesError> {
+        let mutator = self.lineage.mutator.with_site_rates(rates, self.len())?;
+        Ok(SparseGenome {
+            lineage: Lineage::new(self.lineage.reference.clone(), mutator),
+            ..self
+        })
+    }
+
+    /// Whether both genomes share the same reference and mutation model.
+    #[cfg(feature = "serde")]
+    fn same_lineage(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.lineage, &other.lineage)
+    }
+
+    /// Apply serialized variants, checking that they are within the genome.
+    #[cfg(feature = "serde")]
+    fn with_variants(mut self, variants: Vec<(usize, char)>) -> Result<Self, String> {
+        for (pos, base) in variants {
+            if pos >= self.len() || !base.is_ascii() {
+                return Err(format!("invalid variant {}{}", pos + 1, base));
+            }
+            self.set_base(pos, base as u8);
+        }
+        Ok(self)
+    }
+
+    fn base_at(&self, pos: usize) -> u8 {
+        match self.variants.binary_search_by_key(&pos, |x| x.0) {
+            Ok(i) => self.variants[i].1,
+            Err(_) => self.lineage.reference[pos],
+        }
+    }
+
+    fn set_base(&mut self, pos: usize, base: u8) {
+        let is_reference = self.lineage.reference[pos] == base;
+        match self.variants.binary_search_by_key(&pos, |x| x.0) {
+            Ok(i) if is_reference => {
+                self.variants.remove(i);
+            }
+            Ok(i) => self.variants[i].1 = base,
+            Err(_) if is_reference => {}
+            Err(i) => self.variants.insert(i, (pos, base)),
+        }
+    }
+}
+
+impl From<NucleotideGenome> for SparseGenome {
+    /// Use the sequence of `genome` as the shared reference, keeping its substitution model.
+    fn from(genome: NucleotideGenome) -> Self {
+        let (sequence, mutator) = genome.into_parts();
+        let mutator = Arc::try_unwrap(mutator).unwrap_or_else(|x| (*x).clone());
+        SparseGenome {
+            lineage: Lineage::new(sequence.into(), mutator),
+            variants: Vec::new(),
+        }
+    }
+}
+
+impl Genome for SparseGenome {
+    /// Apply exactly `n_mutations` substitutions.
+    ///
+    /// Sites are chosen with replacement, so multiple hits and back-mutations are possible. A
+    /// back-mutation to the reference nucleotide removes the site from the mutation list.
+    fn mutate<R: Rng>(&self, n_mutations: usize, mut rng: R) -> Self {
+        let mut new_genome = self.clone();
+        if n_mutations == 0 {
+            return new_genome;
+        }
+        let Lineage {
+            reference,
+            mutator,
+            n_mutable,
+        } = &*self.lineage;
+        let mut n_mutable = *n_mutable;
+        for &(pos, base) in &self.variants {
+            n_mutable += usize::from(mutator.is_mutable(pos, base));
+            n_mutable -= usize::from(mutator.is_mutable(pos, reference[pos]));
+        }
+        for _ in 0..n_mutations {
+            if n_mutable == 0 {
+                break;
+            }
+            let (pos, base) = mutator.substitution(self.len(), |i| new_genome.base_at(i), &mut rng);
+            new_genome.set_base(pos, base);
+            if !mutator.is_mutable(pos, base) {
+                n_mutable -= 1;
+            }
+        }
+        new_genome
+    }
+
+    /// Counts the sites where the nucleotides differ.
+    ///
+    /// Only the mutation lists are compared when both genomes share the same reference.
+    fn snps(&self, other: &Self) -> u32 {
+        if !Arc::ptr_eq(&self.lineage.reference, &other.lineage.reference) {
+            return self
+                .sequence()
+                .iter()
+                .zip(other.sequence())
+                .filter(|(a, b)| **a != *b)
+                .count()
+                .try_into()
+                .unwrap();
+        }
+
+        let (mut a, mut b) = (
+            self.variants.iter().peekable(),
+            other.variants.iter().peekable(),
+        );
+        let mut snps = 0;
+        loop {
+            match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) => match x.0.cmp(&y.0) {
+                    Ordering::Less => {
+                        snps += 1;
+                        a.next();
+                    }
+                    Ordering::Greater => {
+                        snps += 1;
+                        b.next();
+                    }
+                    Ordering::Equal => {
+                        if x.1 != y.1 {
+                            snps += 1;
+                        }
+                        a.next();
+                        b.next();
+                    }
+                },
+                (Some(_), None) | (None, Some(_)) => {
+                    snps += a.count() + b.count();
+                    break;
+                }
+                (None, None) => break,
+            }
+        }
+        snps.try_into().unwrap()
+    }
+
+    fn write_nucleotides<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut start = 0;
+        for &(pos, base) in &self.variants {
+            writer.write_all(&self.lineage.reference[start..pos])?;
+            writer.write_all(&[base])?;
+            start = pos + 1;
+        }
+        writer.write_all(&self.lineage.reference[start..])
+    }
+}
+
+#[cfg(feature = "serde")]
+thread_local! {
+    /// Genomes with each reference seen so far in a [`share_references`] scope, without variants.
+    static REFERENCES: RefCell<Option<Vec<SparseGenome>>> = const { RefCell::new(None) };
+}
+
+/// Serialize or deserialize within `f` so that each reference is only written once.
+///
+/// The first genome with each reference is written in full, and later genomes give the number of
+/// the reference in order of first appearance. Since serde reads values in the order they were
+/// written, deserialization within the same kind of scope finds each reference before it is used.
+#[cfg(feature = "serde")]
+pub(crate) fn share_references<F: FnOnce() -> T, T>(f: F) -> T {
+    struct Restore(Option<Vec<SparseGenome>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            REFERENCES.with(|x| *x.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(REFERENCES.with(|x| x.replace(Some(Vec::new()))));
+    f()
+}
+
+/// Serialized form of [`SparseGenome`].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum SparseData {
+    /// A genome whose reference was written earlier in the same scope.
+    Shared {
+        reference: usize,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        variants: Vec<(usize, char)>,
+    },
+
+    /// A genome with its full reference.
+    Full(Box<NucleotideData>),
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SparseGenome {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let variants = self
+            .variants
+            .iter()
+            .map(|(pos, base)| (*pos, char::from(*base)))
+            .collect();
+        let shared = REFERENCES.with(|x| {
+            let mut references = x.borrow_mut();
+            let references = references.as_mut()?;
+            let found = references.iter().position(|x| x.same_lineage(self));
+            if found.is_none() {
+                references.push(SparseGenome {
+                    variants: Vec::new(),
+                    ..self.clone()
+                });
+            }
+            found
+        });
+
+        match shared {
+            Some(reference) => SparseData::Shared {
+                reference,
+                variants,
+            },
+            None => SparseData::Full(Box::new(NucleotideData {
+                model: *self.lineage.mutator.matrix(),
+                site_rates: self.lineage.mutator.site_rates().cloned(),
+                sequence: String::from_utf8_lossy(&self.lineage.reference).into_owned(),
+                variants,
+            })),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SparseGenome {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        match SparseData::deserialize(deserializer)? {
+            SparseData::Shared {
+                reference,
+                variants,
+            } => {
+                let genome =
+                    REFERENCES.with(|x| Some(x.borrow().as_ref()?.get(reference)?.clone()));
+                let genome = genome.ok_or_else(|| {
+                    D::Error::custom(format!("reference {} has not been defined", reference))
+                })?;
+                genome.with_variants(variants).map_err(D::Error::custom)
+            }
+            SparseData::Full(data) => {
+                let variants = data.variants;
+                let reference = NucleotideGenome::try_from(NucleotideData {
+                    variants: Vec::new(),
+                    ..*data
+                })
+                .map_err(D::Error::custom)?;
+                let genome = SparseGenome::from(reference);
+                REFERENCES.with(|x| {
+                    if let Some(references) = x.borrow_mut().as_mut() {
+                        references.push(genome.clone());
+                    }
+                });
+                genome.with_variants(variants).map_err(D::Error::custom)
+            }
+        }
+    }
+}
+
+impl PartialEq for SparseGenome {
+    fn eq(&self, other: &Self) -> bool {
+        if Arc::ptr_eq(&self.lineage.reference, &other.lineage.reference) {
+            self.variants == other.variants
+        } else {
+            self.sequence() == other.sequence()
+        }
+    }
+}
+
+impl Eq for SparseGenome {}
+
+impl fmt::Debug for SparseGenome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SparseGenome(")?;
+        for (i, (pos, base)) in self.variants.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            let from = char::from(self.lineage.reference[*pos]);
+            write!(f, "{}{}{}", from, pos + 1, char::from(*base))?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genome::substitution::Jc69;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    #[test]
+    fn test_matches_sequence() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let genome = SparseGenome::from(NucleotideGenome::random(200, &Jc69, &mut rng));
+        let child1 = genome.mutate(20, &mut rng);
+        let child2 = child1.mutate(20, &mut rng);
+
+        let mut written = Vec::new();
+        child2.write_nucleotides(&mut written).unwrap();
+        assert_eq!(written, child2.sequence());
+        assert!(child2.variants().windows(2).all(|x| x[0].0 < x[1].0));
+
+        let expected = child1
+            .sequence()
+            .iter()
+            .zip(child2.sequence())
+            .filter(|(a, b)| **a != *b)
+            .count() as u32;
+        assert_eq!(child1.snps(&child2), expected);
+        assert_eq!(child2.snps(&child1), expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use crate::genome::site_rates::SiteRates;
+
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let genome = SparseGenome::from(NucleotideGenome::random(20, &Jc69, &mut rng))
+            .with_site_rates(SiteRates::gamma(20, 0.5, &mut rng).unwrap())
+            .unwrap()
+            .mutate(3, &mut rng);
+
+        let json = serde_json::to_string(&genome).unwrap();
+        let restored: SparseGenome = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.sequence(), genome.sequence());
+        assert_eq!(restored.variants(), genome.variants());
+
+        let full: NucleotideGenome = serde_json::from_str(&json.replace("variants", "_")).unwrap();
+        assert_eq!(full.sequence(), genome.reference());
+
+        let lineage = vec![
+            genome.clone(),
+            genome.mutate(2, &mut rng),
+            genome.mutate(4, &mut rng),
+        ];
+        let json = share_references(|| serde_json::to_string(&lineage).unwrap());
+        assert_eq!(json.matches("sequence").count(), 1);
+        assert!(serde_json::from_str::<Vec<SparseGenome>>(&json).is_err());
+        let restored: Vec<SparseGenome> = share_references(|| serde_json::from_str(&json).unwrap());
+        assert_eq!(restored, lineage);
+        assert!(Arc::ptr_eq(&restored[0].lineage, &restored[2].lineage));
+    }
+
+    #[test]
+    fn test_back_mutation() {
+        let genome = SparseGenome::from(NucleotideGenome::new("ACGT", &Jc69).unwrap());
+        let mut child = genome.clone();
+        child.set_base(2, b'T');
+        assert_eq!(child.variants(), &[(2, b'T')]);
+        child.set_base(2, b'G');
+        assert!(child.variants().is_empty());
+        assert_eq!(child, genome);
+    }
+}
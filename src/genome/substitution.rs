@@ -0,0 +1,283 @@
+//! Nucleotide substitution models.
+//!
+//! Each model describes a time-reversible instantaneous rate matrix over the four nucleotide
+//! states. The states are always ordered A, C, G, T.
+
+use thiserror::Error;
+
+/// Nucleotide states in the order used by [`RateMatrix`].
+pub(crate) const NUCLEOTIDES: [u8; 4] = [b'A', b'C', b'G', b'T'];
+
+/// Position of a nucleotide in [`NUCLEOTIDES`], or `None` for any other symbol.
+#[inline]
+pub(crate) fn state_index(base: u8) -> Option<usize> {
+    match base {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
+    }
+}
+
+/// Error constructing a substitution model from invalid parameters.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ModelError {
+    #[error("substitution rate parameters must be finite and non-negative")]
+    InvalidRate,
+
+    #[error("base frequencies must be non-negative and sum to one")]
+    InvalidFrequencies,
+}
+
+/// Instantaneous rate matrix `Q` of a substitution model.
+///
+/// Rows and columns are ordered A, C, G, T. The matrix is normalised so that the expected rate of
+/// substitution at equilibrium is one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "RateMatrixData", try_from = "RateMatrixData")
+)]
+pub struct RateMatrix {
+    rates: [[f64; 4]; 4],
+    exchangeabilities: [f64; 6],
+    frequencies: [f64; 4],
+}
+
+/// Parameters of a [`RateMatrix`], which are validated by [`RateMatrix::new`] when deserialized.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RateMatrixData {
+    exchangeabilities: [f64; 6],
+    frequencies: [f64; 4],
+}
+
+#[cfg(feature = "serde")]
+impl From<RateMatrix> for RateMatrixData {
+    fn from(matrix: RateMatrix) -> Self {
+        RateMatrixData {
+            exchangeabilities: matrix.exchangeabilities,
+            frequencies: matrix.frequencies,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RateMatrixData> for RateMatrix {
+    type Error = ModelError;
+
+    fn try_from(data: RateMatrixData) -> Result<Self, Self::Error> {
+        RateMatrix::new(data.exchangeabilities, data.frequencies)
+    }
+}
+
+impl RateMatrix {
+    /// Build the general time-reversible rate matrix.
+    ///
+    /// `exchangeabilities` are the symmetric rates for the pairs AC, AG, AT, CG, CT and GT, and
+    /// `frequencies` are the equilibrium base frequencies of A, C, G and T.
+    pub fn new(exchangeabilities: [f64; 6], frequencies: [f64; 4]) -> Result<Self, ModelError> {
+        if exchangeabilities.iter().any(|x| !x.is_finite() || *x < 0.0) {
+            return Err(ModelError::InvalidRate);
+        }
+        let total: f64 = frequencies.iter().sum();
+        if frequencies.iter().any(|x| !x.is_finite() || *x < 0.0) || (total - 1.0).abs() > 1e-6 {
+            return Err(ModelError::InvalidFrequencies);
+        }
+
+        let mut rates = [[0.0; 4]; 4];
+        let pairs = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+        for (&(i, j), &s) in pairs.iter().zip(&exchangeabilities) {
+            rates[i][j] = s * frequencies[j];
+            rates[j][i] = s * frequencies[i];
+        }
+        for (i, row) in rates.iter_mut().enumerate() {
+            row[i] = -row.iter().sum::<f64>();
+        }
+
+        let mean_rate: f64 = -(0..4).map(|i| frequencies[i] * rates[i][i]).sum::<f64>();
+        if mean_rate <= 0.0 {
+            return Err(ModelError::InvalidRate);
+        }
+        for rate in rates.iter_mut().flatten() {
+            *rate /= mean_rate;
+        }
+
+        Ok(RateMatrix {
+            rates,
+            exchangeabilities,
+            frequencies,
+        })
+    }
+
+    /// Entries of the rate matrix.
+    #[inline]
+    pub fn rates(&self) -> &[[f64; 4]; 4] {
+        &self.rates
+    }
+
+    /// Equilibrium base frequencies.
+    #[inline]
+    pub fn frequencies(&self) -> &[f64; 4] {
+        &self.frequencies
+    }
+
+    /// Total rate of leaving state `i`.
+    #[inline]
+    pub(crate) fn leaving_rate(&self, i: usize) -> f64 {
+        -self.rates[i][i]
+    }
+}
+
+/// Implemented by nucleotide substitution models.
+pub trait SubstitutionModel {
+    /// The instantaneous rate matrix of the model.
+    fn rate_matrix(&self) -> RateMatrix;
+}
+
+impl SubstitutionModel for RateMatrix {
+    fn rate_matrix(&self) -> RateMatrix {
+        *self
+    }
+}
+
+/// Jukes-Cantor model: all substitutions occur at the same rate with equal base frequencies.
+///
+/// Jukes, T.H. and Cantor, C.R. Evolution of protein molecules.
+/// In _Mammalian Protein Metabolism_, 21–132 (1969).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Jc69;
+
+impl SubstitutionModel for Jc69 {
+    fn rate_matrix(&self) -> RateMatrix {
+        RateMatrix::new([1.0; 6], [0.25; 4]).unwrap()
+    }
+}
+
+/// Kimura two-parameter model: transitions occur `kappa` times as often as transversions.
+///
+/// Kimura, M. A simple method for estimating evolutionary rates of base substitutions through
+/// comparative studies of nucleotide sequences. _J Mol Evol_ **16**, 111–120 (1980).
+/// doi: [10.1007/BF01731581](https://doi.org/10.1007/BF01731581)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct K80 {
+    kappa: f64,
+}
+
+impl K80 {
+    /// Create the model from the transition/transversion rate ratio.
+    pub fn new(kappa: f64) -> Result<Self, ModelError> {
+        if !kappa.is_finite() || kappa < 0.0 {
+            return Err(ModelError::InvalidRate);
+        }
+        Ok(K80 { kappa })
+    }
+}
+
+impl SubstitutionModel for K80 {
+    fn rate_matrix(&self) -> RateMatrix {
+        let k = self.kappa;
+        RateMatrix::new([1.0, k, 1.0, 1.0, k, 1.0], [0.25; 4]).unwrap()
+    }
+}
+
+/// Hasegawa-Kishino-Yano model: K80 with unequal base frequencies.
+///
+/// Hasegawa, M., Kishino, H. and Yano, T. Dating of the human-ape splitting by a molecular clock
+/// of mitochondrial DNA. _J Mol Evol_ **22**, 160–174 (1985).
+/// doi: [10.1007/BF02101694](https://doi.org/10.1007/BF02101694)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hky85 {
+    matrix: RateMatrix,
+}
+
+impl Hky85 {
+    /// Create the model from the transition/transversion rate ratio and base frequencies.
+    pub fn new(kappa: f64, frequencies: [f64; 4]) -> Result<Self, ModelError> {
+        let k = kappa;
+        let matrix = RateMatrix::new([1.0, k, 1.0, 1.0, k, 1.0], frequencies)?;
+        Ok(Hky85 { matrix })
+    }
+}
+
+impl SubstitutionModel for Hky85 {
+    fn rate_matrix(&self) -> RateMatrix {
+        self.matrix
+    }
+}
+
+/// General time-reversible model.
+///
+/// Tavaré, S. Some probabilistic and statistical problems in the analysis of DNA sequences.
+/// _Lectures on Mathematics in the Life Sciences_ **17**, 57–86 (1986).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gtr {
+    matrix: RateMatrix,
+}
+
+impl Gtr {
+    /// Create the model from the exchangeabilities and base frequencies.
+    ///
+    /// See [`RateMatrix::new`] for the order of the parameters.
+    pub fn new(exchangeabilities: [f64; 6], frequencies: [f64; 4]) -> Result<Self, ModelError> {
+        let matrix = RateMatrix::new(exchangeabilities, frequencies)?;
+        Ok(Gtr { matrix })
+    }
+}
+
+impl SubstitutionModel for Gtr {
+    fn rate_matrix(&self) -> RateMatrix {
+        self.matrix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_matrix_normalised() {
+        let freqs = [0.1, 0.2, 0.3, 0.4];
+        let q = Gtr::new([1.0, 2.0, 3.0, 4.0, 5.0, 6.0], freqs)
+            .unwrap()
+            .rate_matrix();
+        for row in q.rates() {
+            assert!(row.iter().sum::<f64>().abs() < 1e-12);
+        }
+        let mean_rate: f64 = (0..4).map(|i| freqs[i] * q.leaving_rate(i)).sum();
+        assert!((mean_rate - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_k80_transitions() {
+        let q = K80::new(4.0).unwrap().rate_matrix();
+        let rates = q.rates();
+        assert!((rates[0][2] - 4.0 * rates[0][1]).abs() < 1e-12);
+        assert!((rates[1][3] - 4.0 * rates[1][2]).abs() < 1e-12);
+        assert_eq!(q, Hky85::new(4.0, [0.25; 4]).unwrap().rate_matrix());
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        assert_eq!(K80::new(-1.0), Err(ModelError::InvalidRate));
+        assert_eq!(
+            Hky85::new(2.0, [0.5, 0.5, 0.5, 0.5]),
+            Err(ModelError::InvalidFrequencies)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_validated() {
+        let q = Hky85::new(4.0, [0.3, 0.2, 0.2, 0.3]).unwrap().rate_matrix();
+        let json = serde_json::to_string(&q).unwrap();
+        assert_eq!(serde_json::from_str::<RateMatrix>(&json).unwrap(), q);
+
+        let zero = r#"{"exchangeabilities":[0,0,0,0,0,0],"frequencies":[0.25,0.25,0.25,0.25]}"#;
+        assert!(serde_json::from_str::<RateMatrix>(zero).is_err());
+        let negative = zero.replacen('0', "-1", 1);
+        assert!(serde_json::from_str::<RateMatrix>(&negative).is_err());
+    }
+}
@@ -0,0 +1,340 @@
+use crate::genome::Genome;
+use crate::Time;
+use rand::Rng;
+use std::io;
+
+/// How the sequence of a case is derived from its within-host population.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Sampling {
+    /// The majority nucleotide at each site.
+    Consensus,
+
+    /// A single haplotype drawn at random.
+    Draw,
+}
+
+/// Configuration for [`WithinHost`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WithinHostConfig {
+    /// Number of haplotypes tracked in each host.
+    pub population_size: usize,
+
+    /// Number of haplotypes passed on at each transmission.
+    pub bottleneck: usize,
+
+    /// How the sequence of the case is derived from the haplotypes.
+    pub sampling: Sampling,
+}
+
+/// A population of haplotypes evolving within a host.
+///
+/// Each case carries `population_size` haplotypes of the underlying genome type `G`. Over each
+/// unit of time the population undergoes one generation of Wright-Fisher resampling (genetic
+/// drift), and then each haplotype accumulates mutations at the mutation rate of the simulation.
+/// At transmission, `bottleneck` haplotypes are drawn from the infector's population and found
+/// the population of the new case.
+///
+/// The sequence of a case, as written by [`write_nucleotides`](Genome::write_nucleotides) and
+/// compared by [`snps`](Genome::snps), is the consensus or a random haplotype according to the
+/// configured [`Sampling`]. Minor variants are available from
+/// [`minor_variants`](WithinHost::minor_variants).
+///
+/// This multiplies the memory use and mutation cost of `G` by the population size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "WithinHostData<G>")
+)]
+pub struct WithinHost<G> {
+    haplotypes: Vec<G>,
+    config: WithinHostConfig,
+}
+
+/// Unvalidated form of [`WithinHost`] used for deserialization.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct WithinHostData<G> {
+    haplotypes: Vec<G>,
+    config: WithinHostConfig,
+}
+
+#[cfg(feature = "serde")]
+impl<G> TryFrom<WithinHostData<G>> for WithinHost<G> {
+    type Error = String;
+
+    fn try_from(data: WithinHostData<G>) -> Result<Self, Self::Error> {
+        if data.config.population_size == 0 || data.config.bottleneck == 0 {
+            return Err(INVALID_CONFIG.to_owned());
+        }
+        if data.haplotypes.len() != data.config.population_size {
+            return Err(format!(
+                "expected {} haplotypes but found {}",
+                data.config.population_size,
+                data.haplotypes.len()
+            ));
+        }
+        Ok(WithinHost {
+            haplotypes: data.haplotypes,
+            config: data.config,
+        })
+    }
+}
+
+const INVALID_CONFIG: &str = "within-host population size and bottleneck must be positive";
+
+/// A nucleotide that is present in only part of a within-host population.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinorVariant {
+    /// Zero-based site in the genome.
+    pub position: usize,
+
+    /// The minor nucleotide.
+    pub base: u8,
+
+    /// Proportion of haplotypes carrying the nucleotide.
+    pub frequency: f64,
+}
+
+impl<G: Genome> WithinHost<G> {
+    /// Start a clonal population from a single genome.
+    ///
+    /// Panics if the population size or bottleneck is zero.
+    pub fn new(genome: G, config: WithinHostConfig) -> Self {
+        assert!(
+            config.population_size > 0 && config.bottleneck > 0,
+            "{}",
+            INVALID_CONFIG
+        );
+        WithinHost {
+            haplotypes: vec![genome; config.population_size],
+            config,
+        }
+    }
+
+    /// All haplotypes in the population.
+    #[inline]
+    pub fn haplotypes(&self) -> &[G] {
+        &self.haplotypes
+    }
+
+    /// Nucleotides that are not the consensus at their site, with at least `min_frequency`.
+    pub fn minor_variants(&self, min_frequency: f64) -> Vec<MinorVariant> {
+        let sequences = self.sequences();
+        let n = sequences.len() as f64;
+        let consensus = consensus(&sequences);
+        let mut variants = Vec::new();
+        for (position, major) in consensus.iter().enumerate() {
+            let mut counts = [0usize; 256];
+            for seq in &sequences {
+                counts[seq[position] as usize] += 1;
+            }
+            for (base, count) in counts.iter().enumerate() {
+                let frequency = *count as f64 / n;
+                if base as u8 != *major && *count > 0 && frequency >= min_frequency {
+                    variants.push(MinorVariant {
+                        position,
+                        base: base as u8,
+                        frequency,
+                    });
+                }
+            }
+        }
+        variants
+    }
+
+    fn sequences(&self) -> Vec<Vec<u8>> {
+        self.haplotypes
+            .iter()
+            .map(|x| {
+                let mut seq = Vec::new();
+                // writing to a Vec cannot fail
+                x.write_nucleotides(&mut seq).unwrap();
+                seq
+            })
+            .collect()
+    }
+
+    fn resample<R: Rng>(&self, from: &[G], mut rng: R) -> Vec<G> {
+        (0..self.config.population_size)
+            .map(|_| from[rng.gen_range(0..from.len())].clone())
+            .collect()
+    }
+}
+
+fn consensus(sequences: &[Vec<u8>]) -> Vec<u8> {
+    let len = sequences.iter().map(Vec::len).min().unwrap_or(0);
+    (0..len)
+        .map(|i| {
+            let mut counts = [0usize; 256];
+            for seq in sequences {
+                counts[seq[i] as usize] += 1;
+            }
+            // ties are broken by the first haplotype so that clonal sites are unchanged
+            let first = sequences[0][i];
+            (0..=255u8).fold(first, |best, base| {
+                if counts[base as usize] > counts[best as usize] {
+                    base
+                } else {
+                    best
+                }
+            })
+        })
+        .collect()
+}
+
+impl<G: Genome> Genome for WithinHost<G> {
+    /// Apply `n_mutations` mutations, each to a haplotype chosen at random.
+    fn mutate<R: Rng>(&self, n_mutations: usize, mut rng: R) -> Self {
+        let n = self.haplotypes.len();
+        let mut counts = vec![0; n];
+        for _ in 0..n_mutations {
+            counts[rng.gen_range(0..n)] += 1;
+        }
+        let haplotypes = self
+            .haplotypes
+            .iter()
+            .zip(counts)
+            .map(|(x, n)| {
+                if n > 0 {
+                    x.mutate(n, &mut rng)
+                } else {
+                    x.clone()
+                }
+            })
+            .collect();
+        WithinHost {
+            haplotypes,
+            config: self.config,
+        }
+    }
+
+    /// Evolve the population for `generation_time` generations of drift and mutation.
+    fn mutate_time<R: Rng>(&self, generation_time: Time, mutation_rate: f64, mut rng: R) -> Self {
+        let mut haplotypes = self.haplotypes.clone();
+        for _ in 0..generation_time {
+            haplotypes = self
+                .resample(&haplotypes, &mut rng)
+                .into_iter()
+                .map(|x| x.mutate_time(1, mutation_rate, &mut rng))
+                .collect();
+        }
+        WithinHost {
+            haplotypes,
+            config: self.config,
+        }
+    }
+
+    /// Resamples the population for each whole time step in the interval, and then mutates the
+    /// haplotypes over the remaining fraction of a time step without resampling.
+    fn mutate_interval<R: Rng>(&self, interval: f64, mutation_rate: f64, mut rng: R) -> Self {
+        let steps = interval.max(0.0).floor();
+        let evolved = self.mutate_time(steps as Time, mutation_rate, &mut rng);
+        WithinHost {
+            haplotypes: evolved
+                .haplotypes
+                .iter()
+                .map(|x| x.mutate_interval(interval - steps, mutation_rate, &mut rng))
+                .collect(),
+            config: self.config,
+        }
+    }
+
+    /// Found a new population from `bottleneck` haplotypes drawn at random.
+    fn transmit<R: Rng>(&self, mut rng: R) -> Self {
+        let founders: Vec<G> = (0..self.config.bottleneck)
+            .map(|_| self.haplotypes[rng.gen_range(0..self.haplotypes.len())].clone())
+            .collect();
+        WithinHost {
+            haplotypes: self.resample(&founders, &mut rng),
+            config: self.config,
+        }
+    }
+
+    /// Compares the sampled sequences of the two populations.
+    fn snps(&self, other: &Self) -> u32 {
+        match self.config.sampling {
+            Sampling::Draw => self.haplotypes[0].snps(&other.haplotypes[0]),
+            Sampling::Consensus => {
+                let a = consensus(&self.sequences());
+                let b = consensus(&other.sequences());
+                a.iter()
+                    .zip(&b)
+                    .filter(|(x, y)| x != y)
+                    .count()
+                    .try_into()
+                    .unwrap()
+            }
+        }
+    }
+
+    fn write_nucleotides<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        match self.config.sampling {
+            // haplotypes are exchangeable, so the first is a random draw
+            Sampling::Draw => self.haplotypes[0].write_nucleotides(writer),
+            Sampling::Consensus => writer.write_all(&consensus(&self.sequences())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genome::simple::SimpleGenome;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    fn config(bottleneck: usize) -> WithinHostConfig {
+        WithinHostConfig {
+            population_size: 20,
+            bottleneck,
+            sampling: Sampling::Consensus,
+        }
+    }
+
+    #[test]
+    fn test_diversity() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let host = WithinHost::new(SimpleGenome::<64>::default(), config(1));
+        assert!(host.minor_variants(0.0).is_empty());
+
+        let evolved = host.mutate_time(5, 2.0, &mut rng);
+        assert_eq!(evolved.haplotypes().len(), 20);
+        assert!(!evolved.minor_variants(0.0).is_empty());
+
+        let child = evolved.transmit(&mut rng);
+        assert!(child
+            .haplotypes()
+            .iter()
+            .all(|x| *x == child.haplotypes()[0]));
+
+        let evolved = child.mutate_interval(2.5, 2.0, &mut rng);
+        assert_eq!(evolved.haplotypes().len(), 20);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_validated() {
+        let host = WithinHost::new(SimpleGenome::<4>::default(), config(1));
+        let json = serde_json::to_string(&host).unwrap();
+        assert_eq!(
+            serde_json::from_str::<WithinHost<_>>(&json).ok(),
+            Some(host)
+        );
+
+        let no_bottleneck = json.replace("\"bottleneck\":1", "\"bottleneck\":0");
+        assert!(serde_json::from_str::<WithinHost<SimpleGenome<4>>>(&no_bottleneck).is_err());
+        let empty =
+            r#"{"haplotypes":[],"config":{"population_size":20,"bottleneck":1,"sampling":"Draw"}}"#;
+        assert!(serde_json::from_str::<WithinHost<SimpleGenome<4>>>(empty).is_err());
+    }
+
+    #[test]
+    fn test_consensus() {
+        let sequences = vec![b"ACGT".to_vec(), b"ACCA".to_vec(), b"TCCT".to_vec()];
+        assert_eq!(consensus(&sequences), b"ACCT");
+    }
+}
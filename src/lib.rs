@@ -24,7 +24,7 @@
 //! let mutation_rate = 2e-4 / 365.0 * 30000.0;
 //!
 //! // use a seedable RNG for reproducibility
-//! let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924 as u64);
+//! let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
 //! // stop the simulation at the end of a time step where the case count exceeds 100
 //! let max_cases = 100;
 //!
@@ -37,15 +37,32 @@
 //! assert_eq!(ob.sources(), vec![None, Some(0), Some(1), Some(1), Some(1)]);
 //! ```
 //!
+//! For an event-driven simulation in continuous time, use
+//! [`simulate_continuous`](simulate::simulate_continuous) with a
+//! [`ContinuousDisease`](simple::ContinuousDisease) model.
+//!
 //! See the examples directory for more ways of configuring the simulations, e.g.
 //! `cargo run --example combined`.
+//!
+//! # Features
+//! * `serde`: implements `Serialize` and `Deserialize` for outbreaks, case histories, genomes and
+//!   simulation configurations, so that results can be saved and reloaded.
+//! * `rayon`: runs many replicates of a simulation in parallel with
+//!   [`ensemble`](simulate::ensemble), and fills size bins in parallel with
+//!   [`binned_outbreaks_par`](simulate::binned_outbreaks_par), with reproducible random number
+//!   streams for each replicate.
+//! * `cli`: builds the `obsim` command-line tool, which runs a simulation described by a TOML or
+//!   YAML configuration file.
 
 pub mod case;
+pub mod clock;
 mod disease;
 mod genome;
+pub mod population;
+pub mod schedule;
 pub mod simulate;
 
-pub use disease::DiseaseModel;
+pub use disease::{ContinuousDiseaseModel, DiseaseModel};
 pub use genome::Genome;
 pub use simulate::outbreak::Outbreak;
 pub use simulate::simulate_outbreak;
@@ -59,8 +76,104 @@ pub mod simple {
     //! These models make a number of simplifying assumptions, but are a reasonable starting point
     //! and can be configured to match details of a pathogen of interest.
 
+    pub use crate::disease::continuous::ContinuousDisease;
     pub use crate::disease::simple::SimpleDisease;
-    pub use crate::genome::simple::SimpleGenome;
+    pub use crate::genome::simple::{DynSimpleGenome, SimpleGenome};
+    pub use crate::genome::site_rates::{SiteRates, SiteRatesError};
+}
+
+pub mod models {
+    //! Disease models with parameters from the published literature.
+    //!
+    //! Each model is constructed with published defaults for the natural history of the disease.
+    //! The reporting time and reproduction number depend on the surveillance system and on the
+    //! immunity and contacts of the population, so they are always provided by the caller. All
+    //! fields are public so that the defaults can be adjusted. Time steps are days.
+    //!
+    //! [`Covid`] has its own model of infectiousness since exposure. Influenza, measles, mpox and
+    //! pertussis are presets of [`OnsetDisease`], where infectiousness is anchored to symptom
+    //! onset.
+
+    pub use crate::disease::covid::Covid;
+    pub use crate::disease::onset::OnsetDisease;
+}
+
+pub mod nucleotide {
+    //! Genomes with four nucleotide states and standard substitution models.
+    //!
+    //! These produce alignments with realistic base composition and transition/transversion
+    //! patterns, at the cost of storing one byte per site. For long genomes, [`SparseGenome`]
+    //! stores only the differences from a shared reference sequence.
+    //!
+    //! By default every site is equally likely to mutate. Use [`SiteRates`] to model mutational
+    //! hotspots, conserved regions and invariant sites.
+    //!
+    //! ```
+    //! use obsim::nucleotide::{Hky85, NucleotideGenome};
+    //! use obsim::simple::SimpleDisease;
+    //! use obsim::simulate::{rounded_poisson, simulate_outbreak};
+    //! use rand::SeedableRng;
+    //! use rand_distr::Gamma;
+    //! use rand_xoshiro::Xoshiro256PlusPlus;
+    //!
+    //! let disease_model = SimpleDisease {
+    //!     incubation_time: rounded_poisson(1.).unwrap(),
+    //!     reporting_time: rounded_poisson(1.).unwrap(),
+    //!     reproduction_number: Gamma::new(1.5, 0.75).unwrap(),
+    //!     infectiousness: vec![0.34, 0.33, 0.33],
+    //! };
+    //! let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+    //!
+    //! // transitions are 4 times as likely as transversions, and the genome is AT-rich
+    //! let model = Hky85::new(4.0, [0.3, 0.2, 0.2, 0.3]).unwrap();
+    //! let genome = NucleotideGenome::random(1000, &model, &mut rng);
+    //! let ob = simulate_outbreak(genome, &disease_model, 0.05, 100, &mut rng);
+    //! assert!(ob.is_ok());
+    //! ```
+
+    pub use crate::genome::nucleotide::{NucleotideGenome, SequenceError};
+    pub use crate::genome::site_rates::{SiteRates, SiteRatesError};
+    pub use crate::genome::sparse::SparseGenome;
+    pub use crate::genome::substitution::{
+        Gtr, Hky85, Jc69, ModelError, RateMatrix, SubstitutionModel, K80,
+    };
+}
+
+pub mod within_host {
+    //! Within-host diversity and transmission bottlenecks.
+    //!
+    //! Wrap any [`Genome`](crate::Genome) in a [`WithinHost`] so that each case carries a small
+    //! population of haplotypes rather than a single genome.
+    //!
+    //! ```
+    //! use obsim::simple::{SimpleDisease, SimpleGenome};
+    //! use obsim::simulate::{rounded_poisson, simulate_outbreak};
+    //! use obsim::within_host::{Sampling, WithinHost, WithinHostConfig};
+    //! use rand::SeedableRng;
+    //! use rand_distr::Gamma;
+    //! use rand_xoshiro::Xoshiro256PlusPlus;
+    //!
+    //! let disease_model = SimpleDisease {
+    //!     incubation_time: rounded_poisson(1.).unwrap(),
+    //!     reporting_time: rounded_poisson(1.).unwrap(),
+    //!     reproduction_number: Gamma::new(1.5, 0.75).unwrap(),
+    //!     infectiousness: vec![0.34, 0.33, 0.33],
+    //! };
+    //! let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+    //!
+    //! // track 50 haplotypes per case, and transmit 2 of them to each new case
+    //! let config = WithinHostConfig {
+    //!     population_size: 50,
+    //!     bottleneck: 2,
+    //!     sampling: Sampling::Consensus,
+    //! };
+    //! let genome = WithinHost::new(SimpleGenome::<256>::default(), config);
+    //! let ob = simulate_outbreak(genome, &disease_model, 0.5, 100, &mut rng);
+    //! let ob = ob.unwrap_or_else(|err| *err.outbreak);
+    //! let isnvs = ob.genomes()[0].minor_variants(0.05);
+    //! ```
+
+    pub use crate::genome::within_host::{MinorVariant, Sampling, WithinHost, WithinHostConfig};
 }
 
 pub use disease::covid;
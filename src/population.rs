@@ -0,0 +1,323 @@
+//! Models of the population that an outbreak spreads through.
+//!
+//! A population decides how many new infections occur in each time step and who infects them.
+//! [`simulate_outbreak`](crate::simulate_outbreak) uses an [`Unlimited`] population where there
+//! is always someone left to infect. Use
+//! [`simulate_outbreak_in`](crate::simulate::simulate_outbreak_in) with a [`WellMixed`]
+//! population to deplete the susceptibles so that large outbreaks burn out by themselves, or with
+//! [`Households`] for transmission within households and workplaces, with a [`ContactNetwork`]
+//! for transmission between neighbours in a network, or with a [`Metapopulation`] for spread
+//! between named regions.
+
+use crate::Count;
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use rand_distr::{Binomial, Distribution, Poisson};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+mod households;
+mod metapopulation;
+mod network;
+pub use households::{HouseholdConfig, HouseholdState, Households};
+pub use metapopulation::{Metapopulation, MetapopulationState, Region};
+pub use network::{ContactNetwork, NetworkError};
+
+/// Error constructing a population from invalid parameters.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PopulationError {
+    #[error("population must not be empty")]
+    Empty,
+
+    #[error("household sizes must be non-negative weights with a positive sum")]
+    InvalidHouseholdSizes,
+
+    #[error("proportions must be between zero and one")]
+    InvalidProportion,
+
+    #[error("transmission rates and multipliers must be finite and non-negative")]
+    InvalidTransmission,
+
+    #[error("mobility must be a square matrix of non-negative weights with one row per region")]
+    InvalidMobility,
+
+    #[error("region name {0:?} is empty, repeated, or contains whitespace or `,=[]`")]
+    InvalidRegionName(String),
+
+    #[error("no region named {0:?}")]
+    UnknownRegion(String),
+}
+
+/// Setting where a case was infected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Setting {
+    /// Infected by a member of the same household.
+    Household,
+
+    /// Infected by someone attending the same workplace or school.
+    Workplace,
+
+    /// Infected by anyone else in the population.
+    Community,
+
+    /// Infected by a neighbour in a contact network.
+    Contact,
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Setting::Household => "household",
+            Setting::Workplace => "workplace",
+            Setting::Community => "community",
+            Setting::Contact => "contact",
+        })
+    }
+}
+
+/// A new infection chosen by a [`Population`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Infection {
+    /// The case that caused the infection.
+    pub infector: Count,
+
+    /// Where the infection happened.
+    pub setting: Setting,
+}
+
+/// Implemented by models of the population that an outbreak spreads through.
+///
+/// Each time step of a simulation first calls [`transmit`](Population::transmit) to determine the
+/// number of new infections, and then calls [`next_infection`](Population::next_infection) once
+/// for each of them. The state is rebuilt with [`start`](Population::start) for each independent
+/// outbreak.
+pub trait Population {
+    type State;
+
+    /// Prepare the state for a new outbreak, which starts with a single index case.
+    fn start<R: Rng>(&self, rng: R) -> Self::State;
+
+    /// Determine the new infections in one time step and return how many there are.
+    ///
+    /// `infectivity` has the infectivity of every case in the outbreak so far during this time
+    /// step, indexed by case.
+    fn transmit<R: Rng>(&self, state: &mut Self::State, infectivity: &[f64], rng: R) -> Count;
+
+    /// Choose the infector of the next new infection from the latest time step.
+    ///
+    /// New cases are numbered in the order that they are returned here.
+    fn next_infection<R: Rng>(&self, state: &mut Self::State, rng: R) -> Infection;
+
+    /// The region label of a case, for populations that have regions.
+    ///
+    /// This is called for the index case after [`start`](Population::start), and for each new case
+    /// after [`next_infection`](Population::next_infection).
+    fn region(&self, _state: &Self::State, _case: Count) -> Option<Arc<str>> {
+        None
+    }
+}
+
+impl<P: Population> Population for &P {
+    type State = P::State;
+
+    #[inline]
+    fn start<R: Rng>(&self, rng: R) -> Self::State {
+        (*self).start(rng)
+    }
+
+    #[inline]
+    fn transmit<R: Rng>(&self, state: &mut Self::State, infectivity: &[f64], rng: R) -> Count {
+        (*self).transmit(state, infectivity, rng)
+    }
+
+    #[inline]
+    fn next_infection<R: Rng>(&self, state: &mut Self::State, rng: R) -> Infection {
+        (*self).next_infection(state, rng)
+    }
+
+    #[inline]
+    fn region(&self, state: &Self::State, case: Count) -> Option<Arc<str>> {
+        (*self).region(state, case)
+    }
+}
+
+/// Population where everyone is equally likely to be infected and nobody ever becomes immune.
+///
+/// The number of new infections in each time step follows a Poisson distribution with mean equal
+/// to the total infectivity of all cases, so outbreaks with a reproduction number above one can
+/// grow without limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unlimited;
+
+impl Population for Unlimited {
+    type State = Option<WeightedIndex<f64>>;
+
+    fn start<R: Rng>(&self, _rng: R) -> Self::State {
+        None
+    }
+
+    fn transmit<R: Rng>(&self, state: &mut Self::State, infectivity: &[f64], mut rng: R) -> Count {
+        let total_infectivity: f64 = infectivity.iter().sum();
+        if total_infectivity <= 0.0 {
+            return 0;
+        }
+        let new_cases = Poisson::new(total_infectivity).unwrap().sample(&mut rng) as Count;
+        if new_cases > 0 {
+            *state = Some(WeightedIndex::new(infectivity).unwrap());
+        }
+        new_cases
+    }
+
+    fn next_infection<R: Rng>(&self, state: &mut Self::State, mut rng: R) -> Infection {
+        Infection {
+            infector: state.as_ref().unwrap().sample(&mut rng) as Count,
+            setting: Setting::Community,
+        }
+    }
+}
+
+/// Finite population where everyone is equally likely to be in contact.
+///
+/// This follows a discrete-time SIR (Reed-Frost) model. Each of the remaining susceptibles escapes
+/// infection in a time step with probability `exp(-λ / size)`, where `λ` is the total infectivity
+/// of all cases. Cases are immune once infected, so the rate of new infections saturates as the
+/// susceptibles are depleted and every outbreak eventually ends.
+///
+/// While most of the population is susceptible this matches [`Unlimited`].
+#[derive(Debug, Clone, Copy)]
+pub struct WellMixed {
+    /// Number of individuals, including the index case.
+    pub size: Count,
+}
+
+/// State of a [`WellMixed`] population.
+#[derive(Debug, Clone)]
+pub struct WellMixedState {
+    susceptible: Count,
+    infectors: Option<WeightedIndex<f64>>,
+}
+
+impl WellMixedState {
+    /// Number of individuals not yet infected.
+    #[inline]
+    pub fn susceptible(&self) -> Count {
+        self.susceptible
+    }
+}
+
+impl Population for WellMixed {
+    type State = WellMixedState;
+
+    fn start<R: Rng>(&self, _rng: R) -> Self::State {
+        WellMixedState {
+            susceptible: self.size.saturating_sub(1),
+            infectors: None,
+        }
+    }
+
+    fn transmit<R: Rng>(&self, state: &mut Self::State, infectivity: &[f64], mut rng: R) -> Count {
+        let total_infectivity: f64 = infectivity.iter().sum();
+        if total_infectivity <= 0.0 || state.susceptible == 0 {
+            return 0;
+        }
+        let p_infection = -(-total_infectivity / self.size as f64).exp_m1();
+        let new_cases = Binomial::new(state.susceptible.into(), p_infection)
+            .unwrap()
+            .sample(&mut rng) as Count;
+        if new_cases > 0 {
+            state.susceptible -= new_cases;
+            state.infectors = Some(WeightedIndex::new(infectivity).unwrap());
+        }
+        new_cases
+    }
+
+    fn next_infection<R: Rng>(&self, state: &mut Self::State, mut rng: R) -> Infection {
+        Infection {
+            infector: state.infectors.as_ref().unwrap().sample(&mut rng) as Count,
+            setting: Setting::Community,
+        }
+    }
+}
+
+/// State of a population of distinct individuals, such as [`Households`] or [`ContactNetwork`].
+#[derive(Debug, Clone)]
+pub struct IndividualState {
+    infected: Vec<bool>,
+    individual: Vec<Count>,
+    susceptible: Count,
+    pending: VecDeque<(Infection, Count)>,
+}
+
+impl IndividualState {
+    /// Start with one infected `index` individual in a population of `size`.
+    fn new(size: Count, index: Count) -> Self {
+        let mut infected = vec![false; size as usize];
+        infected[index as usize] = true;
+        IndividualState {
+            infected,
+            individual: vec![index],
+            susceptible: size - 1,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Number of individuals not yet infected.
+    #[inline]
+    pub fn susceptible(&self) -> Count {
+        self.susceptible
+    }
+
+    /// The individual that each case is, indexed by case.
+    #[inline]
+    pub fn individuals(&self) -> &[Count] {
+        &self.individual
+    }
+
+    #[inline]
+    fn is_infected(&self, individual: Count) -> bool {
+        self.infected[individual as usize]
+    }
+
+    /// Infect an individual, to become a case at the next call to `next_infection`.
+    fn infect(&mut self, individual: Count, infection: Infection) {
+        self.infected[individual as usize] = true;
+        self.susceptible -= 1;
+        self.pending.push_back((infection, individual));
+    }
+
+    fn n_pending(&self) -> Count {
+        self.pending.len() as Count
+    }
+
+    fn next_infection(&mut self) -> Infection {
+        let (infection, individual) = self.pending.pop_front().unwrap();
+        self.individual.push(individual);
+        infection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    #[test]
+    fn test_depletion() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let population = WellMixed { size: 10 };
+        let mut state = population.start(&mut rng);
+        assert_eq!(state.susceptible(), 9);
+
+        let infectivity = [100.0, 0.0];
+        assert_eq!(population.transmit(&mut state, &infectivity, &mut rng), 9);
+        assert_eq!(state.susceptible(), 0);
+        let infection = population.next_infection(&mut state, &mut rng);
+        assert_eq!(infection.infector, 0);
+        assert_eq!(population.transmit(&mut state, &infectivity, &mut rng), 0);
+    }
+}
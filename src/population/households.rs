@@ -0,0 +1,319 @@
+use super::{IndividualState, Infection, Population, PopulationError, Setting};
+use crate::Count;
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use rand_distr::{Binomial, Distribution};
+use std::collections::BTreeMap;
+
+/// Configuration for [`Households`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HouseholdConfig {
+    /// Number of households.
+    pub n_households: Count,
+
+    /// Relative frequency of households with 1, 2, 3, ... members.
+    pub household_sizes: Vec<f64>,
+
+    /// Number of workplaces and schools, or zero for no workplace transmission.
+    pub n_workplaces: Count,
+
+    /// Proportion of individuals who attend a workplace or school.
+    pub workplace_attendance: f64,
+
+    /// Rate of transmission to each other member of the household, per unit of infectivity.
+    pub household_rate: f64,
+
+    /// Rate of transmission to each other attendee of the workplace, per unit of infectivity.
+    pub workplace_rate: f64,
+
+    /// Multiplier on infectivity for transmission to anyone in the population.
+    ///
+    /// With a value of one, the community transmission alone matches [`WellMixed`](super::WellMixed).
+    pub community_rate: f64,
+}
+
+/// State of a [`Households`] population while an outbreak is simulated.
+pub type HouseholdState = IndividualState;
+
+/// Finite population structured into households and workplaces.
+///
+/// Each individual belongs to one household, and may attend one workplace or school. Every case
+/// exerts a force of infection in each of its settings that is proportional to its infectivity:
+///
+/// * each susceptible member of its household is infected with probability
+///   `1 - exp(-household_rate * λ)` in a time step with total household infectivity `λ`
+/// * likewise for the other attendees of its workplace, with `workplace_rate`
+/// * in the community, the population is well-mixed as for [`WellMixed`](super::WellMixed), with
+///   the infectivity multiplied by `community_rate`
+///
+/// Individuals are immune once infected. The setting of each infection is recorded in
+/// [`Outbreak::settings`](crate::Outbreak::settings). Household transmission tends to occur soon
+/// after the infector becomes infectious, so household clusters are usually genomically
+/// near-identical.
+///
+/// The structure is generated once by [`Households::new`] and reused by every outbreak simulated
+/// in it. Each outbreak starts from an individual chosen at random.
+#[derive(Debug, Clone)]
+pub struct Households {
+    config: HouseholdConfig,
+    household: Vec<Count>,
+    household_start: Vec<usize>,
+    workplace: Vec<Option<Count>>,
+    workplace_members: Vec<Vec<Count>>,
+}
+
+impl Households {
+    /// Generate the households and workplaces.
+    ///
+    /// Household sizes are drawn from `household_sizes`, and each individual attends a workplace
+    /// chosen uniformly at random with probability `workplace_attendance`. The transmission rates
+    /// must be finite and non-negative.
+    pub fn new<R: Rng>(config: HouseholdConfig, mut rng: R) -> Result<Self, PopulationError> {
+        let sizes = WeightedIndex::new(&config.household_sizes)
+            .map_err(|_| PopulationError::InvalidHouseholdSizes)?;
+        if config.n_households == 0 {
+            return Err(PopulationError::Empty);
+        }
+        if !(0.0..=1.0).contains(&config.workplace_attendance) {
+            return Err(PopulationError::InvalidProportion);
+        }
+        let rates = [
+            config.household_rate,
+            config.workplace_rate,
+            config.community_rate,
+        ];
+        if rates.iter().any(|x| !(*x >= 0.0 && x.is_finite())) {
+            return Err(PopulationError::InvalidTransmission);
+        }
+
+        let mut household = Vec::new();
+        let mut household_start = vec![0];
+        for h in 0..config.n_households {
+            let size = sizes.sample(&mut rng) + 1;
+            household.resize(household.len() + size, h);
+            household_start.push(household.len());
+        }
+
+        let mut workplace = vec![None; household.len()];
+        let mut workplace_members = vec![Vec::new(); config.n_workplaces as usize];
+        if config.n_workplaces > 0 {
+            for (i, w) in workplace.iter_mut().enumerate() {
+                if rng.gen_bool(config.workplace_attendance) {
+                    let chosen = rng.gen_range(0..config.n_workplaces);
+                    *w = Some(chosen);
+                    workplace_members[chosen as usize].push(i as Count);
+                }
+            }
+        }
+
+        Ok(Households {
+            config,
+            household,
+            household_start,
+            workplace,
+            workplace_members,
+        })
+    }
+
+    /// Number of individuals.
+    #[inline]
+    pub fn size(&self) -> Count {
+        self.household.len() as Count
+    }
+
+    /// The household of each individual.
+    #[inline]
+    pub fn households(&self) -> &[Count] {
+        &self.household
+    }
+
+    /// The workplace of each individual, if any.
+    #[inline]
+    pub fn workplaces(&self) -> &[Option<Count>] {
+        &self.workplace
+    }
+
+    fn household_members(&self, household: Count) -> impl Iterator<Item = Count> {
+        let h = household as usize;
+        (self.household_start[h] as Count)..(self.household_start[h + 1] as Count)
+    }
+
+    /// Infect susceptible members of each group, given the infectious cases in each group.
+    fn transmit_within<I, R>(
+        &self,
+        state: &mut IndividualState,
+        groups: BTreeMap<Count, Vec<(Count, f64)>>,
+        rate: f64,
+        setting: Setting,
+        members: impl Fn(Count) -> I,
+        mut rng: R,
+    ) where
+        I: IntoIterator<Item = Count>,
+        R: Rng,
+    {
+        for (group, infectors) in groups {
+            let total: f64 = infectors.iter().map(|x| x.1).sum();
+            let p_infection = -(-rate * total).exp_m1();
+            let infector_dist = WeightedIndex::new(infectors.iter().map(|x| x.1)).unwrap();
+            for member in members(group) {
+                if state.is_infected(member) || !rng.gen_bool(p_infection) {
+                    continue;
+                }
+                let infector = infectors[infector_dist.sample(&mut rng)].0;
+                state.infect(member, Infection { infector, setting });
+            }
+        }
+    }
+}
+
+impl Population for Households {
+    type State = HouseholdState;
+
+    fn start<R: Rng>(&self, mut rng: R) -> Self::State {
+        IndividualState::new(self.size(), rng.gen_range(0..self.size()))
+    }
+
+    fn transmit<R: Rng>(&self, state: &mut Self::State, infectivity: &[f64], mut rng: R) -> Count {
+        let mut households: BTreeMap<Count, Vec<(Count, f64)>> = BTreeMap::new();
+        let mut workplaces: BTreeMap<Count, Vec<(Count, f64)>> = BTreeMap::new();
+        for (case, &x) in infectivity.iter().enumerate() {
+            if x <= 0.0 {
+                continue;
+            }
+            let individual = state.individuals()[case] as usize;
+            let infector = (case as Count, x);
+            households
+                .entry(self.household[individual])
+                .or_default()
+                .push(infector);
+            if let Some(w) = self.workplace[individual] {
+                workplaces.entry(w).or_default().push(infector);
+            }
+        }
+
+        self.transmit_within(
+            state,
+            households,
+            self.config.household_rate,
+            Setting::Household,
+            |h| self.household_members(h),
+            &mut rng,
+        );
+        self.transmit_within(
+            state,
+            workplaces,
+            self.config.workplace_rate,
+            Setting::Workplace,
+            |w| self.workplace_members[w as usize].iter().copied(),
+            &mut rng,
+        );
+
+        let total_infectivity: f64 = infectivity.iter().sum();
+        if total_infectivity > 0.0 && state.susceptible() > 0 && self.config.community_rate > 0.0 {
+            let lambda = self.config.community_rate * total_infectivity / self.size() as f64;
+            let new_cases = Binomial::new(state.susceptible().into(), -(-lambda).exp_m1())
+                .unwrap()
+                .sample(&mut rng);
+            let infector_dist = WeightedIndex::new(infectivity).unwrap();
+            for _ in 0..new_cases {
+                let individual = loop {
+                    let i = rng.gen_range(0..self.size());
+                    if !state.is_infected(i) {
+                        break i;
+                    }
+                };
+                let infector = infector_dist.sample(&mut rng) as Count;
+                let setting = Setting::Community;
+                state.infect(individual, Infection { infector, setting });
+            }
+        }
+
+        state.n_pending()
+    }
+
+    fn next_infection<R: Rng>(&self, state: &mut Self::State, _rng: R) -> Infection {
+        state.next_infection()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disease::simple::test_disease;
+    use crate::genome::simple::SimpleGenome;
+    use crate::simulate::simulate_outbreak_in;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    fn config() -> HouseholdConfig {
+        HouseholdConfig {
+            n_households: 100,
+            household_sizes: vec![0.0, 0.0, 0.0, 1.0],
+            n_workplaces: 0,
+            workplace_attendance: 0.0,
+            household_rate: 5.0,
+            workplace_rate: 0.0,
+            community_rate: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_structure() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let population = Households::new(config(), &mut rng).unwrap();
+        assert_eq!(population.size(), 400);
+        assert_eq!(population.households()[7], 1);
+        assert!(population.household_members(3).eq(12..16));
+
+        let mut bad = config();
+        bad.household_sizes = vec![0.0];
+        assert!(Households::new(bad, &mut rng).is_err());
+
+        for rate in [-1.0, f64::NAN, f64::INFINITY] {
+            let bad = HouseholdConfig {
+                household_rate: rate,
+                ..config()
+            };
+            let err = Households::new(bad, &mut rng).unwrap_err();
+            assert_eq!(err, PopulationError::InvalidTransmission);
+            let bad = HouseholdConfig {
+                community_rate: rate,
+                ..config()
+            };
+            assert!(Households::new(bad, &mut rng).is_err());
+        }
+    }
+
+    #[test]
+    fn test_household_transmission() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let dm = test_disease(2.0, 1.0);
+        let population = Households::new(config(), &mut rng).unwrap();
+        let genome = SimpleGenome::<64>::default();
+        let outbreak = simulate_outbreak_in(genome, &dm, &population, 1.0, 0.1, 100, &mut rng);
+
+        let outbreak = outbreak.unwrap();
+        assert!(outbreak.n_cases() > 1);
+        assert!(outbreak.n_cases() <= 4);
+        assert!(outbreak.settings()[1..]
+            .iter()
+            .all(|x| *x == Some(Setting::Household)));
+
+        let mixed = HouseholdConfig {
+            n_workplaces: 5,
+            workplace_attendance: 0.5,
+            workplace_rate: 0.2,
+            community_rate: 1.0,
+            ..config()
+        };
+        let population = Households::new(mixed, &mut rng).unwrap();
+        let genome = SimpleGenome::<64>::default();
+        let outbreak = simulate_outbreak_in(genome, &dm, &population, 1.0, 0.1, 400, &mut rng);
+
+        let outbreak = outbreak.unwrap_or_else(|err| *err.outbreak);
+        for setting in [Setting::Household, Setting::Workplace, Setting::Community] {
+            assert!(outbreak.settings().contains(&Some(setting)));
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use super::{Infection, Population, PopulationError, Setting};
+use crate::Count;
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use rand_distr::{Binomial, Distribution};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// A region of a [`Metapopulation`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    /// Label of the region in the outputs, see [`Outbreak::regions`](crate::Outbreak::regions).
+    pub name: String,
+
+    /// Number of individuals living in the region.
+    pub size: Count,
+
+    /// Multiplier on infectivity for transmission within the region.
+    pub transmission: f64,
+}
+
+/// Finite population divided into regions that are each well-mixed, with movement between them.
+///
+/// Each case lives in one region, but spends some of its time in the others. Row `i` of the
+/// mobility matrix gives the relative amount of time that residents of region `i` spend in each
+/// region, so that a case with infectivity `λ` living in region `i` exerts infectivity
+/// `mobility[i][j] * λ` in region `j`. The rows are normalised to sum to one, so movement does not
+/// change the total infectivity of a case. An identity matrix gives independent regions.
+///
+/// Within each region `j`, the susceptible residents are infected as for
+/// [`WellMixed`](super::WellMixed), with the total infectivity exerted there multiplied by the
+/// `transmission` of the region. New cases live in the region where they were infected, and
+/// their region is recorded in [`Outbreak::regions`](crate::Outbreak::regions).
+///
+/// Each outbreak starts in a region chosen with probability proportional to its size, unless set
+/// with [`with_index_region`](Metapopulation::with_index_region).
+#[derive(Debug, Clone)]
+pub struct Metapopulation {
+    names: Vec<Arc<str>>,
+    sizes: Vec<Count>,
+    transmission: Vec<f64>,
+    mobility: Vec<Vec<f64>>,
+    index_region: Option<usize>,
+}
+
+impl Metapopulation {
+    /// Build a metapopulation from its regions and the mobility between them.
+    ///
+    /// `mobility` must have one row and one column for each region, in the same order.
+    pub fn new(regions: Vec<Region>, mobility: Vec<Vec<f64>>) -> Result<Self, PopulationError> {
+        if regions.iter().all(|x| x.size == 0) {
+            return Err(PopulationError::Empty);
+        }
+        for (i, region) in regions.iter().enumerate() {
+            let valid = !region.name.is_empty()
+                && !region
+                    .name
+                    .contains(|c: char| c.is_whitespace() || ",=[]".contains(c))
+                && regions[..i].iter().all(|x| x.name != region.name);
+            if !valid {
+                return Err(PopulationError::InvalidRegionName(region.name.clone()));
+            }
+            if !(region.transmission >= 0.0 && region.transmission.is_finite()) {
+                return Err(PopulationError::InvalidTransmission);
+            }
+        }
+
+        if mobility.len() != regions.len() {
+            return Err(PopulationError::InvalidMobility);
+        }
+        let mut normalised = Vec::with_capacity(mobility.len());
+        for row in mobility {
+            let total: f64 = row.iter().sum();
+            let valid = row.len() == regions.len()
+                && row.iter().all(|x| *x >= 0.0)
+                && total > 0.0
+                && total.is_finite();
+            if !valid {
+                return Err(PopulationError::InvalidMobility);
+            }
+            normalised.push(row.into_iter().map(|x| x / total).collect());
+        }
+
+        Ok(Metapopulation {
+            names: regions.iter().map(|x| x.name.as_str().into()).collect(),
+            sizes: regions.iter().map(|x| x.size).collect(),
+            transmission: regions.iter().map(|x| x.transmission).collect(),
+            mobility: normalised,
+            index_region: None,
+        })
+    }
+
+    /// Start every outbreak in the region with this name.
+    pub fn with_index_region(self, name: &str) -> Result<Self, PopulationError> {
+        match self.names.iter().position(|x| **x == *name) {
+            Some(i) if self.sizes[i] > 0 => Ok(Metapopulation {
+                index_region: Some(i),
+                ..self
+            }),
+            Some(_) => Err(PopulationError::Empty),
+            None => Err(PopulationError::UnknownRegion(name.to_owned())),
+        }
+    }
+
+    /// Total number of individuals in all regions.
+    pub fn size(&self) -> Count {
+        self.sizes.iter().sum()
+    }
+
+    /// Names of the regions.
+    #[inline]
+    pub fn names(&self) -> &[Arc<str>] {
+        &self.names
+    }
+
+    /// Number of individuals in each region.
+    #[inline]
+    pub fn sizes(&self) -> &[Count] {
+        &self.sizes
+    }
+
+    /// The mobility matrix, with each row normalised to sum to one.
+    #[inline]
+    pub fn mobility(&self) -> &[Vec<f64>] {
+        &self.mobility
+    }
+}
+
+/// State of a [`Metapopulation`].
+#[derive(Debug, Clone)]
+pub struct MetapopulationState {
+    susceptible: Vec<Count>,
+    region: Vec<usize>,
+    pending: VecDeque<(Infection, usize)>,
+}
+
+impl MetapopulationState {
+    /// Number of individuals not yet infected in each region.
+    #[inline]
+    pub fn susceptible(&self) -> &[Count] {
+        &self.susceptible
+    }
+
+    /// The region of each case, as an index into [`Metapopulation::names`].
+    #[inline]
+    pub fn regions(&self) -> &[usize] {
+        &self.region
+    }
+}
+
+impl Population for Metapopulation {
+    type State = MetapopulationState;
+
+    fn start<R: Rng>(&self, mut rng: R) -> Self::State {
+        let index = self
+            .index_region
+            .unwrap_or_else(|| WeightedIndex::new(&self.sizes).unwrap().sample(&mut rng));
+        let mut susceptible = self.sizes.clone();
+        susceptible[index] -= 1;
+        MetapopulationState {
+            susceptible,
+            region: vec![index],
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn transmit<R: Rng>(&self, state: &mut Self::State, infectivity: &[f64], mut rng: R) -> Count {
+        for j in 0..self.names.len() {
+            if state.susceptible[j] == 0 || self.transmission[j] <= 0.0 {
+                continue;
+            }
+            let exerted: Vec<f64> = infectivity
+                .iter()
+                .zip(&state.region)
+                .map(|(x, &i)| self.transmission[j] * self.mobility[i][j] * x)
+                .collect();
+            let total: f64 = exerted.iter().sum();
+            if total <= 0.0 {
+                continue;
+            }
+            let p_infection = -(-total / self.sizes[j] as f64).exp_m1();
+            let new_cases = Binomial::new(state.susceptible[j].into(), p_infection)
+                .unwrap()
+                .sample(&mut rng) as Count;
+            if new_cases == 0 {
+                continue;
+            }
+            state.susceptible[j] -= new_cases;
+            let infector_dist = WeightedIndex::new(&exerted).unwrap();
+            for _ in 0..new_cases {
+                let infector = infector_dist.sample(&mut rng) as Count;
+                let setting = Setting::Community;
+                state
+                    .pending
+                    .push_back((Infection { infector, setting }, j));
+            }
+        }
+        state.pending.len() as Count
+    }
+
+    fn next_infection<R: Rng>(&self, state: &mut Self::State, _rng: R) -> Infection {
+        let (infection, region) = state.pending.pop_front().unwrap();
+        state.region.push(region);
+        infection
+    }
+
+    fn region(&self, state: &Self::State, case: Count) -> Option<Arc<str>> {
+        Some(self.names[state.region[case as usize]].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disease::simple::test_disease;
+    use crate::genome::simple::SimpleGenome;
+    use crate::simulate::simulate_outbreak_in;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    fn regions() -> Vec<Region> {
+        ["north", "south"]
+            .into_iter()
+            .map(|name| Region {
+                name: name.to_owned(),
+                size: 200,
+                transmission: 1.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_validation() {
+        let population = Metapopulation::new(regions(), vec![vec![3.0, 1.0], vec![0.0, 2.0]]);
+        let population = population.unwrap();
+        assert_eq!(population.size(), 400);
+        assert_eq!(population.mobility()[0], [0.75, 0.25]);
+
+        let err = population.with_index_region("east").unwrap_err();
+        assert_eq!(err, PopulationError::UnknownRegion("east".to_owned()));
+
+        let bad_mobility = vec![vec![1.0, 0.0], vec![0.0, 0.0]];
+        let err = Metapopulation::new(regions(), bad_mobility).unwrap_err();
+        assert_eq!(err, PopulationError::InvalidMobility);
+
+        let mut bad_regions = regions();
+        bad_regions[1].name = "south east".to_owned();
+        let err = Metapopulation::new(bad_regions, vec![vec![1.0; 2]; 2]).unwrap_err();
+        assert!(matches!(err, PopulationError::InvalidRegionName(_)));
+    }
+
+    #[test]
+    fn test_regional_spread() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let dm = test_disease(30.0, 0.1);
+        let isolated = Metapopulation::new(regions(), vec![vec![1.0, 0.0], vec![0.0, 1.0]])
+            .unwrap()
+            .with_index_region("south")
+            .unwrap();
+        let genome = SimpleGenome::<64>::default();
+        let outbreak = simulate_outbreak_in(genome, &dm, &isolated, 1.0, 0.1, Count::MAX, &mut rng);
+
+        let outbreak = outbreak.unwrap();
+        assert!(outbreak.n_cases() > 1);
+        assert!(outbreak.n_cases() <= 200);
+        assert!(outbreak
+            .regions()
+            .iter()
+            .all(|x| x.as_deref() == Some("south")));
+
+        let connected = Metapopulation::new(regions(), vec![vec![1.0, 1.0]; 2]).unwrap();
+        let genome = SimpleGenome::<64>::default();
+        let outbreak =
+            simulate_outbreak_in(genome, &dm, &connected, 1.0, 0.1, Count::MAX, &mut rng);
+
+        let outbreak = outbreak.unwrap();
+        for region in ["north", "south"] {
+            assert!(outbreak
+                .regions()
+                .iter()
+                .any(|x| x.as_deref() == Some(region)));
+        }
+        let mut fasta = Vec::new();
+        outbreak.write_fasta(&mut fasta).unwrap();
+        assert!(String::from_utf8(fasta)
+            .unwrap()
+            .contains(" region=north\n"));
+    }
+}
@@ -0,0 +1,353 @@
+use super::{IndividualState, Infection, Population, Setting};
+use crate::Count;
+use rand::distributions::WeightedIndex;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_distr::Distribution;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead};
+use thiserror::Error;
+
+/// Error constructing a [`ContactNetwork`].
+#[derive(Error, Debug)]
+pub enum NetworkError {
+    #[error("network must have at least one node")]
+    Empty,
+
+    #[error("edge to node {node}, but the network only has {n_nodes} nodes")]
+    UnknownNode { node: Count, n_nodes: Count },
+
+    #[error("mean degree must be between zero and the number of other nodes")]
+    InvalidMeanDegree,
+
+    #[error("need at least one edge per node and more nodes than edges per node")]
+    InvalidEdgesPerNode,
+
+    #[error("sum of degrees must be even")]
+    OddDegreeSum,
+
+    #[error("transmission rate must be finite and non-negative")]
+    InvalidTransmission,
+
+    #[error("line {line} of the edge list does not have two nodes")]
+    InvalidEdge { line: usize },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Finite population where transmission happens along the edges of a contact network.
+///
+/// Each node is an individual and each undirected edge is a contact. In each time step, a case
+/// with infectivity `λ` infects each of its susceptible neighbours with probability
+/// `1 - exp(-transmission_rate * λ)`, so a case with `k` susceptible contacts infects about
+/// `k * transmission_rate` times as many others as it would in an [`Unlimited`](super::Unlimited)
+/// population. A `transmission_rate` of one over the mean degree therefore keeps the reproduction
+/// number roughly unchanged on average, while well-connected nodes become superspreaders.
+///
+/// Individuals are immune once infected. Each outbreak starts from a node chosen at random.
+#[derive(Debug, Clone)]
+pub struct ContactNetwork {
+    neighbours: Vec<Vec<Count>>,
+    transmission_rate: f64,
+}
+
+impl ContactNetwork {
+    /// Build a network from undirected edges between nodes numbered from zero to `n_nodes - 1`.
+    ///
+    /// Self-loops and repeated edges are ignored. The transmission rate is one.
+    pub fn from_edges<I>(n_nodes: Count, edges: I) -> Result<Self, NetworkError>
+    where
+        I: IntoIterator<Item = (Count, Count)>,
+    {
+        if n_nodes == 0 {
+            return Err(NetworkError::Empty);
+        }
+        let mut neighbours = vec![Vec::new(); n_nodes as usize];
+        for (a, b) in edges {
+            if let Some(node) = [a, b].into_iter().find(|x| *x >= n_nodes) {
+                return Err(NetworkError::UnknownNode { node, n_nodes });
+            }
+            if a != b {
+                neighbours[a as usize].push(b);
+                neighbours[b as usize].push(a);
+            }
+        }
+        for x in &mut neighbours {
+            x.sort_unstable();
+            x.dedup();
+        }
+        Ok(ContactNetwork {
+            neighbours,
+            transmission_rate: 1.0,
+        })
+    }
+
+    /// Read a network from a list of edges, one per line.
+    ///
+    /// Each line has the labels of two nodes separated by whitespace or a comma. Any further
+    /// columns, such as edge weights, are ignored, as are blank lines and lines starting with `#`.
+    /// Nodes are numbered in the order they first appear.
+    pub fn from_edge_list<R: BufRead>(reader: R) -> Result<Self, NetworkError> {
+        let mut labels: HashMap<String, Count> = HashMap::new();
+        let mut edges = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut nodes = line
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|x| !x.is_empty())
+                .map(|label| {
+                    let next = labels.len() as Count;
+                    *labels.entry(label.to_owned()).or_insert(next)
+                });
+            match (nodes.next(), nodes.next()) {
+                (Some(a), Some(b)) => edges.push((a, b)),
+                _ => return Err(NetworkError::InvalidEdge { line: i + 1 }),
+            }
+        }
+        Self::from_edges(labels.len() as Count, edges)
+    }
+
+    /// Erdős–Rényi random network with `n_nodes` nodes and edges placed uniformly at random.
+    ///
+    /// The number of edges is chosen to give the requested mean degree.
+    pub fn erdos_renyi<R: Rng>(
+        n_nodes: Count,
+        mean_degree: f64,
+        mut rng: R,
+    ) -> Result<Self, NetworkError> {
+        let n = n_nodes as u64;
+        let n_edges = (n as f64 * mean_degree / 2.0).round();
+        if !(n_edges >= 0.0 && n_edges <= (n * n.saturating_sub(1) / 2) as f64) {
+            return Err(NetworkError::InvalidMeanDegree);
+        }
+
+        let mut seen = HashSet::new();
+        let mut edges = Vec::new();
+        while edges.len() < n_edges as usize {
+            let a = rng.gen_range(0..n_nodes);
+            let b = rng.gen_range(0..n_nodes);
+            if a != b && seen.insert((a.min(b), a.max(b))) {
+                edges.push((a, b));
+            }
+        }
+        Self::from_edges(n_nodes, edges)
+    }
+
+    /// Barabási–Albert scale-free network grown by preferential attachment.
+    ///
+    /// The network starts from `m + 1` fully connected nodes, and each further node connects to
+    /// `m` existing nodes chosen with probability proportional to their degree.
+    pub fn barabasi_albert<R: Rng>(
+        n_nodes: Count,
+        m: Count,
+        mut rng: R,
+    ) -> Result<Self, NetworkError> {
+        if m == 0 || n_nodes <= m {
+            return Err(NetworkError::InvalidEdgesPerNode);
+        }
+
+        let mut edges = Vec::new();
+        for a in 0..=m {
+            for b in 0..a {
+                edges.push((a, b));
+            }
+        }
+        // every node appears once for each of its edges
+        let mut endpoints: Vec<Count> = edges.iter().flat_map(|&(a, b)| [a, b]).collect();
+        for node in (m + 1)..n_nodes {
+            let mut targets = Vec::with_capacity(m as usize);
+            while targets.len() < m as usize {
+                let target = endpoints[rng.gen_range(0..endpoints.len())];
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+            for target in targets {
+                edges.push((node, target));
+                endpoints.extend([node, target]);
+            }
+        }
+        Self::from_edges(n_nodes, edges)
+    }
+
+    /// Random network with a given degree sequence, using the configuration model.
+    ///
+    /// The edge ends of all nodes are paired at random. Self-loops and repeated edges are
+    /// discarded, so some nodes can end up with slightly lower degree than requested.
+    pub fn configuration_model<R: Rng>(
+        degrees: &[Count],
+        mut rng: R,
+    ) -> Result<Self, NetworkError> {
+        let mut stubs: Vec<Count> = Vec::new();
+        for (node, &k) in degrees.iter().enumerate() {
+            stubs.resize(stubs.len() + k as usize, node as Count);
+        }
+        if stubs.len() % 2 == 1 {
+            return Err(NetworkError::OddDegreeSum);
+        }
+        stubs.shuffle(&mut rng);
+        let edges = stubs.chunks_exact(2).map(|x| (x[0], x[1]));
+        Self::from_edges(degrees.len() as Count, edges)
+    }
+
+    /// Set the rate of transmission along each edge, per unit of infectivity.
+    ///
+    /// The rate must be finite and non-negative.
+    pub fn with_transmission_rate(self, transmission_rate: f64) -> Result<Self, NetworkError> {
+        if !(transmission_rate >= 0.0 && transmission_rate.is_finite()) {
+            return Err(NetworkError::InvalidTransmission);
+        }
+        Ok(ContactNetwork {
+            transmission_rate,
+            ..self
+        })
+    }
+
+    /// Number of nodes.
+    #[inline]
+    pub fn size(&self) -> Count {
+        self.neighbours.len() as Count
+    }
+
+    /// Number of edges.
+    pub fn n_edges(&self) -> usize {
+        self.neighbours.iter().map(Vec::len).sum::<usize>() / 2
+    }
+
+    /// The neighbours of a node.
+    #[inline]
+    pub fn neighbours(&self, node: Count) -> &[Count] {
+        &self.neighbours[node as usize]
+    }
+
+    /// Number of neighbours of each node.
+    pub fn degrees(&self) -> Vec<Count> {
+        self.neighbours.iter().map(|x| x.len() as Count).collect()
+    }
+}
+
+impl Population for ContactNetwork {
+    type State = IndividualState;
+
+    fn start<R: Rng>(&self, mut rng: R) -> Self::State {
+        IndividualState::new(self.size(), rng.gen_range(0..self.size()))
+    }
+
+    fn transmit<R: Rng>(&self, state: &mut Self::State, infectivity: &[f64], mut rng: R) -> Count {
+        let mut exposed: BTreeMap<Count, Vec<(Count, f64)>> = BTreeMap::new();
+        for (case, &x) in infectivity.iter().enumerate() {
+            if x <= 0.0 {
+                continue;
+            }
+            let node = state.individuals()[case];
+            for &contact in self.neighbours(node) {
+                if !state.is_infected(contact) {
+                    exposed.entry(contact).or_default().push((case as Count, x));
+                }
+            }
+        }
+
+        for (node, infectors) in exposed {
+            let total: f64 = infectors.iter().map(|x| x.1).sum();
+            if !rng.gen_bool(-(-self.transmission_rate * total).exp_m1()) {
+                continue;
+            }
+            let infector = if infectors.len() == 1 {
+                infectors[0].0
+            } else {
+                let dist = WeightedIndex::new(infectors.iter().map(|x| x.1)).unwrap();
+                infectors[dist.sample(&mut rng)].0
+            };
+            let setting = Setting::Contact;
+            state.infect(node, Infection { infector, setting });
+        }
+
+        state.n_pending()
+    }
+
+    fn next_infection<R: Rng>(&self, state: &mut Self::State, _rng: R) -> Infection {
+        state.next_infection()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disease::simple::test_disease;
+    use crate::genome::simple::SimpleGenome;
+    use crate::simulate::simulate_outbreak_in;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    #[test]
+    fn test_edge_list() {
+        let text = "# contacts\nalice bob\nbob,carol,0.5\n\ncarol alice\nalice alice\n";
+        let network = ContactNetwork::from_edge_list(text.as_bytes()).unwrap();
+        assert_eq!(network.size(), 3);
+        assert_eq!(network.n_edges(), 3);
+        assert_eq!(network.neighbours(0), &[1, 2]);
+
+        let err = ContactNetwork::from_edge_list("a b\nc\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, NetworkError::InvalidEdge { line: 2 }));
+    }
+
+    #[test]
+    fn test_generators() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let network = ContactNetwork::erdos_renyi(100, 4.0, &mut rng).unwrap();
+        assert_eq!(network.n_edges(), 200);
+
+        let network = ContactNetwork::barabasi_albert(100, 2, &mut rng).unwrap();
+        assert_eq!(network.n_edges(), 3 + 2 * 97);
+        assert!(network.degrees().iter().all(|k| *k >= 2));
+
+        let network = ContactNetwork::configuration_model(&[3, 3, 3, 3], &mut rng).unwrap();
+        assert!(network.n_edges() <= 6);
+        let err = ContactNetwork::configuration_model(&[1, 2], &mut rng).unwrap_err();
+        assert!(matches!(err, NetworkError::OddDegreeSum));
+
+        let err = ContactNetwork::erdos_renyi(10, 10.0, &mut rng).unwrap_err();
+        assert!(matches!(err, NetworkError::InvalidMeanDegree));
+        let err = ContactNetwork::barabasi_albert(3, 3, &mut rng).unwrap_err();
+        assert!(matches!(err, NetworkError::InvalidEdgesPerNode));
+        let err = ContactNetwork::from_edges(3, [(0, 1), (1, 3)]).unwrap_err();
+        assert!(matches!(
+            err,
+            NetworkError::UnknownNode {
+                node: 3,
+                n_nodes: 3
+            }
+        ));
+
+        for rate in [-1.0, f64::NAN, f64::INFINITY] {
+            let err = network.clone().with_transmission_rate(rate).unwrap_err();
+            assert!(matches!(err, NetworkError::InvalidTransmission));
+        }
+    }
+
+    #[test]
+    fn test_network_transmission() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let dm = test_disease(2.0, 1.0);
+        // two disconnected paths of 5 nodes
+        let edges = (0..9).filter(|x| *x != 4).map(|x| (x, x + 1));
+        let network = ContactNetwork::from_edges(10, edges)
+            .unwrap()
+            .with_transmission_rate(10.0)
+            .unwrap();
+        let genome = SimpleGenome::<64>::default();
+        let outbreak = simulate_outbreak_in(genome, &dm, &network, 1.0, 0.1, 100, &mut rng);
+
+        let outbreak = outbreak.unwrap();
+        assert!(outbreak.n_cases() > 1);
+        assert!(outbreak.n_cases() <= 5);
+        assert!(outbreak.settings()[1..]
+            .iter()
+            .all(|x| *x == Some(Setting::Contact)));
+    }
+}
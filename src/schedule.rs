@@ -0,0 +1,190 @@
+//! Changes in transmission over time.
+//!
+//! A schedule gives a multiplier on the infectivity of every case at each time step, to represent
+//! interventions such as lockdowns, school holidays or vaccination rollouts. Anywhere that a
+//! simulation asks for a `transmission` schedule, a plain `f64` can be given for a constant
+//! multiplier, or a closure of the simulation time such as `|t| if t < 30 { 1.0 } else { 0.4 }`.
+//! A [`Piecewise`] schedule holds the multiplier constant between change points.
+//!
+//! Since the multiplier scales the infectivity rather than the reproduction number distribution
+//! of a particular model, schedules work with any [`DiseaseModel`](crate::DiseaseModel).
+
+use crate::Time;
+use thiserror::Error;
+
+/// Error constructing a [`Piecewise`] schedule.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ScheduleError {
+    #[error("transmission multipliers must be finite and non-negative")]
+    InvalidMultiplier,
+
+    #[error("change at time {at} is not after the previous change at time {previous}")]
+    UnorderedChange { at: Time, previous: Time },
+}
+
+/// Implemented by schedules of a multiplier on transmission.
+pub trait TransmissionSchedule {
+    /// The multiplier on infectivity at time `t`, which must be finite and non-negative.
+    fn multiplier(&self, t: Time) -> f64;
+}
+
+/// Constant multiplier.
+impl TransmissionSchedule for f64 {
+    #[inline]
+    fn multiplier(&self, _t: Time) -> f64 {
+        *self
+    }
+}
+
+impl<F: Fn(Time) -> f64> TransmissionSchedule for F {
+    #[inline]
+    fn multiplier(&self, t: Time) -> f64 {
+        self(t)
+    }
+}
+
+/// Multiplier that is constant between change points.
+///
+/// The multiplier is `initial` until the first change, and then takes the value of the latest
+/// change at or before each time step.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "PiecewiseFields")
+)]
+pub struct Piecewise {
+    initial: f64,
+    changes: Vec<(Time, f64)>,
+}
+
+impl Piecewise {
+    /// Schedule with a constant multiplier of `initial` and no changes yet.
+    pub fn new(initial: f64) -> Result<Self, ScheduleError> {
+        check_multiplier(initial)?;
+        Ok(Piecewise {
+            initial,
+            changes: Vec::new(),
+        })
+    }
+
+    /// Add a change to `multiplier` from time `at` onwards.
+    ///
+    /// Changes must be added in order, each at a later time than the one before.
+    pub fn then(mut self, at: Time, multiplier: f64) -> Result<Self, ScheduleError> {
+        check_multiplier(multiplier)?;
+        if let Some(&(previous, _)) = self.changes.last() {
+            if at <= previous {
+                return Err(ScheduleError::UnorderedChange { at, previous });
+            }
+        }
+        self.changes.push((at, multiplier));
+        Ok(self)
+    }
+
+    /// Multiplier before the first change.
+    #[inline]
+    pub fn initial(&self) -> f64 {
+        self.initial
+    }
+
+    /// Time of each change, with the multiplier from then on.
+    #[inline]
+    pub fn changes(&self) -> &[(Time, f64)] {
+        &self.changes
+    }
+}
+
+fn check_multiplier(multiplier: f64) -> Result<(), ScheduleError> {
+    if !(multiplier >= 0.0 && multiplier.is_finite()) {
+        return Err(ScheduleError::InvalidMultiplier);
+    }
+    Ok(())
+}
+
+impl TransmissionSchedule for Piecewise {
+    fn multiplier(&self, t: Time) -> f64 {
+        match self.changes.partition_point(|x| x.0 <= t) {
+            0 => self.initial,
+            i => self.changes[i - 1].1,
+        }
+    }
+}
+
+/// Unvalidated fields of a [`Piecewise`] schedule, checked when deserializing.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct PiecewiseFields {
+    initial: f64,
+    changes: Vec<(Time, f64)>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<PiecewiseFields> for Piecewise {
+    type Error = ScheduleError;
+
+    fn try_from(fields: PiecewiseFields) -> Result<Self, Self::Error> {
+        let mut schedule = Piecewise::new(fields.initial)?;
+        for (at, multiplier) in fields.changes {
+            schedule = schedule.then(at, multiplier)?;
+        }
+        Ok(schedule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piecewise() {
+        let lockdown = Piecewise::new(1.0)
+            .and_then(|x| x.then(20, 0.3))
+            .and_then(|x| x.then(40, 1.0))
+            .unwrap();
+        assert_eq!(lockdown.multiplier(0), 1.0);
+        assert_eq!(lockdown.multiplier(20), 0.3);
+        assert_eq!(lockdown.multiplier(39), 0.3);
+        assert_eq!(lockdown.multiplier(100), 1.0);
+
+        let closure = |t: Time| if t < 20 { 2.0 } else { 0.5 };
+        assert_eq!(closure.multiplier(25), 0.5);
+    }
+
+    #[test]
+    fn test_invalid_piecewise() {
+        for multiplier in [-0.5, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                Piecewise::new(multiplier),
+                Err(ScheduleError::InvalidMultiplier)
+            );
+            let err = Piecewise::new(1.0).unwrap().then(10, multiplier);
+            assert_eq!(err, Err(ScheduleError::InvalidMultiplier));
+        }
+
+        let err = Piecewise::new(1.0)
+            .and_then(|x| x.then(40, 1.0))
+            .and_then(|x| x.then(20, 0.3));
+        assert_eq!(
+            err,
+            Err(ScheduleError::UnorderedChange {
+                at: 20,
+                previous: 40
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_piecewise() {
+        let lockdown = Piecewise::new(1.0).unwrap().then(20, 0.3).unwrap();
+        let json = serde_json::to_string(&lockdown).unwrap();
+        assert_eq!(json, r#"{"initial":1.0,"changes":[[20,0.3]]}"#);
+        assert_eq!(serde_json::from_str::<Piecewise>(&json).unwrap(), lockdown);
+
+        let unordered = r#"{"initial":1.0,"changes":[[40,1.0],[20,0.3]]}"#;
+        assert!(serde_json::from_str::<Piecewise>(unordered).is_err());
+        let negative = r#"{"initial":-1.0,"changes":[]}"#;
+        assert!(serde_json::from_str::<Piecewise>(negative).is_err());
+    }
+}
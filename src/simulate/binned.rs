@@ -1,16 +1,25 @@
 use rand::Rng;
 use rand_distr::{Distribution, Uniform};
-use std::collections::{BTreeMap, VecDeque};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::fmt;
 use thiserror::Error;
 
-use super::{simulate_outbreak, Outbreak};
+use super::{simulate_outbreak_in, GrowthError, Outbreak};
+use crate::clock::MolecularClock;
 use crate::disease::DiseaseModel;
 use crate::genome::Genome;
+use crate::population::{Population, Unlimited};
+use crate::schedule::TransmissionSchedule;
 use crate::{Count, Time};
 
 /// Configuration for [`binned_outbreaks()`].
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 pub struct BinnedOutbreakConfig {
     /// Edges of the size bins: provide one more edge than the number of bins.
     pub size_bin_edges: Vec<Count>,
@@ -34,6 +43,35 @@ pub struct BinnedOutbreakConfig {
     pub bad_simulation_cap: usize,
 }
 
+/// Problem with a [`BinnedOutbreakConfig`], see [`BinnedOutbreakConfig::validate`].
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum BinConfigError {
+    #[error("size_bin_edges must have at least two edges to define a bin")]
+    EmptyEdges,
+
+    #[error("{edges} size bin edges need {} size counts, but {counts} were given", edges - 1)]
+    MismatchedCounts { edges: usize, counts: usize },
+
+    #[error("size bin edges must be increasing, but edge {index} is not above the previous edge")]
+    NonIncreasingEdges { index: usize },
+
+    #[error("bad_simulation_cap must be at least one")]
+    ZeroSimulationCap,
+
+    #[error("importation time plus the time to the MRCA is too long to represent")]
+    TimeOverflow,
+}
+
+/// Error from [`binned_outbreaks`] and related functions.
+#[derive(Error, Debug)]
+pub enum BinnedError {
+    #[error("invalid binned outbreak configuration: {0}")]
+    Config(#[from] BinConfigError),
+
+    #[error(transparent)]
+    Bins(#[from] BinError),
+}
+
 #[derive(Error, Debug)]
 pub struct BinError {
     discarded: Vec<usize>,
@@ -55,32 +93,77 @@ pub struct BinError {
 ///
 /// See `cargo run --example binned` for an example.
 ///
-/// Panics if `bad_simulation_cap` outbreaks are rejected, since this probably means that the
-/// simulation parameters are producing outbreaks that are too big or too small for the size bin
+/// The ancestral lineages evolve at the mean rate of the `mutation_rate` clock.
+///
+/// Returns an error if the configuration is invalid (see [`BinnedOutbreakConfig::validate`]), or
+/// if `bad_simulation_cap` outbreaks are rejected, since this probably means that the simulation
+/// parameters are producing outbreaks that are too big or too small for the size bin
 /// configuration.
-pub fn binned_outbreaks<D, G, R>(
+///
+/// The times in the result are shifted so that the earliest infection is at time zero.
+///
+/// The outbreaks spread through an [`Unlimited`] population; see [`binned_outbreaks_in`] for
+/// other populations.
+pub fn binned_outbreaks<D, G, C, R>(
     ancestral_genome: G,
     disease_model: &D,
-    mutation_rate: f64,
+    mutation_rate: C,
+    sim_config: &BinnedOutbreakConfig,
+    rng: R,
+) -> Result<Outbreak<G>, BinnedError>
+where
+    D: DiseaseModel,
+    G: Genome,
+    C: MolecularClock,
+    R: Rng,
+{
+    let mut outbreak = binned_outbreaks_in(
+        ancestral_genome,
+        disease_model,
+        Unlimited,
+        1.0,
+        mutation_rate,
+        sim_config,
+        rng,
+    )?;
+    outbreak.rezero_time();
+    Ok(outbreak)
+}
+
+/// Generate and merge many outbreaks, each spreading through a separate population.
+///
+/// This is the same as [`binned_outbreaks`], except that each outbreak is simulated with
+/// [`simulate_outbreak_in`](super::simulate_outbreak_in) in a fresh copy of `population`.
+///
+/// The `transmission` schedule and the times in the result are both measured from the beginning
+/// of the index case window, so an outbreak imported at time `t0` sees the multiplier at time
+/// `t0 + t` in its time step `t`. Unlike [`binned_outbreaks`], the times are not shifted so that
+/// the earliest infection is at time zero, since that would move them off the clock of the
+/// schedule. Use [`Outbreak::rezero_time`] if that is needed.
+pub fn binned_outbreaks_in<D, P, S, G, C, R>(
+    ancestral_genome: G,
+    disease_model: &D,
+    population: P,
+    transmission: S,
+    mutation_rate: C,
     sim_config: &BinnedOutbreakConfig,
     mut rng: R,
-) -> Result<Outbreak<G>, BinError>
+) -> Result<Outbreak<G>, BinnedError>
 where
     D: DiseaseModel,
+    P: Population,
+    S: TransmissionSchedule,
     G: Genome,
+    C: MolecularClock,
     R: Rng,
 {
-    sim_config.validate();
+    sim_config.validate()?;
     let mut size_counts = sim_config.size_counts.clone();
 
     let total_signal: u32 = size_counts.iter().sum();
 
     let mut failed = Vec::new();
-    let mut outbreak = Outbreak {
-        source: Vec::new(),
-        history: Vec::new(),
-        genome: Vec::new(),
-    };
+    let mut outbreak = Outbreak::empty();
 
     let importation_dist = Uniform::from(Time::default()..=sim_config.latest_importation);
     let mut importation_times: VecDeque<Time> = importation_dist
@@ -89,20 +172,22 @@ where
         .collect();
 
     while size_counts.iter().sum::<u32>() > 0 {
-        let generation_time = importation_times[0] + sim_config.time_to_mrca;
-        let genome = ancestral_genome.mutate_time(generation_time, mutation_rate, &mut rng);
-
-        match simulate_outbreak(
-            genome,
+        let imported_at = importation_times[0];
+        match simulate_imported(
+            &ancestral_genome,
             disease_model,
-            mutation_rate,
-            sim_config.max_size(),
+            &population,
+            &transmission,
+            &mutation_rate,
+            sim_config,
+            imported_at,
             &mut rng,
         ) {
             Ok(mut new_ob) => {
                 let size_bin = sim_config.size_bin(new_ob.n_cases() as Count);
                 if accept(&mut size_counts, size_bin) {
-                    new_ob.time_shift(importation_times.pop_front().unwrap());
+                    new_ob.time_shift(imported_at);
+                    importation_times.pop_front();
                     outbreak.extend_with(new_ob);
                 } else {
                     failed.push(new_ob.n_cases());
@@ -118,16 +203,188 @@ where
                 discarded: failed,
                 remaining: size_counts,
                 config: sim_config.clone(),
-            });
+            }
+            .into());
+        }
+    }
+
+    add_background(
+        &mut outbreak,
+        &ancestral_genome,
+        disease_model,
+        &mutation_rate,
+        sim_config,
+        &mut rng,
+    )?;
+    Ok(outbreak)
+}
+
+/// Generate and merge many outbreaks, simulating candidates on multiple threads.
+///
+/// This is the same as [`binned_outbreaks_in`], except that candidate outbreaks are simulated
+/// in parallel batches with [`rayon`]. Each candidate draws its own importation time and is
+/// simulated with its own random number stream from
+/// [`replicate_rngs(master_seed)`](super::replicate_rngs). The candidates are then accepted into
+/// the size bins, or rejected, in the order they were attempted, so the result only depends on
+/// `master_seed` and not on the number of threads. It will differ from the result of
+/// [`binned_outbreaks_in`] with an RNG seeded from the same value.
+#[cfg(feature = "rayon")]
+pub fn binned_outbreaks_par<D, P, S, G, C>(
+    ancestral_genome: G,
+    disease_model: &D,
+    population: P,
+    transmission: S,
+    mutation_rate: C,
+    sim_config: &BinnedOutbreakConfig,
+    master_seed: u64,
+) -> Result<Outbreak<G>, BinnedError>
+where
+    D: DiseaseModel + Sync,
+    P: Population + Sync,
+    S: TransmissionSchedule + Sync,
+    G: Genome + Send + Sync,
+    C: MolecularClock + Sync,
+{
+    use rayon::prelude::*;
+
+    sim_config.validate()?;
+    let mut size_counts = sim_config.size_counts.clone();
+
+    let mut failed = Vec::new();
+    let mut outbreak = Outbreak::empty();
+
+    let mut streams = super::replicate_rngs(master_seed);
+    let mut rng = streams.next().unwrap();
+    let importation_dist = Uniform::from(Time::default()..=sim_config.latest_importation);
+    let batch_size = 4 * rayon::current_num_threads();
+
+    while size_counts.iter().sum::<u32>() > 0 {
+        let batch: Vec<_> = streams.by_ref().take(batch_size).collect();
+        let attempts: Vec<_> = batch
+            .into_par_iter()
+            .map(|mut rng| {
+                let imported_at = importation_dist.sample(&mut rng);
+                let result = simulate_imported(
+                    &ancestral_genome,
+                    disease_model,
+                    &population,
+                    &transmission,
+                    &mutation_rate,
+                    sim_config,
+                    imported_at,
+                    &mut rng,
+                );
+                (imported_at, result)
+            })
+            .collect();
+
+        for (imported_at, result) in attempts {
+            if size_counts.iter().sum::<u32>() == 0 {
+                break;
+            }
+            match result {
+                Ok(mut new_ob) => {
+                    let size_bin = sim_config.size_bin(new_ob.n_cases() as Count);
+                    if accept(&mut size_counts, size_bin) {
+                        new_ob.time_shift(imported_at);
+                        outbreak.extend_with(new_ob);
+                    } else {
+                        failed.push(new_ob.n_cases());
+                    }
+                }
+                Err(new_ob) => {
+                    failed.push(new_ob.outbreak.n_cases());
+                }
+            }
+
+            if failed.len() >= sim_config.bad_simulation_cap {
+                return Err(BinError {
+                    discarded: failed,
+                    remaining: size_counts,
+                    config: sim_config.clone(),
+                }
+                .into());
+            }
         }
     }
 
+    add_background(
+        &mut outbreak,
+        &ancestral_genome,
+        disease_model,
+        &mutation_rate,
+        sim_config,
+        &mut rng,
+    )?;
+    Ok(outbreak)
+}
+
+/// Simulate one candidate outbreak with its index case imported at `imported_at`.
+#[allow(clippy::too_many_arguments)]
+fn simulate_imported<D, P, S, G, C, R>(
+    ancestral_genome: &G,
+    disease_model: &D,
+    population: P,
+    transmission: &S,
+    mutation_rate: &C,
+    sim_config: &BinnedOutbreakConfig,
+    imported_at: Time,
+    mut rng: R,
+) -> Result<Outbreak<G>, GrowthError<G>>
+where
+    D: DiseaseModel,
+    P: Population,
+    S: TransmissionSchedule,
+    G: Genome,
+    C: MolecularClock,
+    R: Rng,
+{
+    let generation_time = imported_at + sim_config.time_to_mrca;
+    let genome = ancestral_genome.mutate_time(generation_time, mutation_rate.mean_rate(), &mut rng);
+
+    simulate_outbreak_in(
+        genome,
+        disease_model,
+        population,
+        |t| transmission.multiplier(imported_at + t),
+        mutation_rate,
+        sim_config.max_size(),
+        &mut rng,
+    )
+}
+
+/// Add the background singletons and sample all genomes.
+///
+/// The singletons are imported at any time up to the end of the merged outbreaks, which can be
+/// later than the index case window checked by [`BinnedOutbreakConfig::validate`].
+fn add_background<D, G, C, R>(
+    outbreak: &mut Outbreak<G>,
+    ancestral_genome: &G,
+    disease_model: &D,
+    mutation_rate: &C,
+    sim_config: &BinnedOutbreakConfig,
+    mut rng: R,
+) -> Result<(), BinConfigError>
+where
+    D: DiseaseModel,
+    G: Genome,
+    C: MolecularClock,
+    R: Rng,
+{
     let last_case = outbreak.end_time().unwrap_or_default();
+    if sim_config.n_background > 0
+        && last_case
+            .checked_add(sim_config.time_to_background_mrca)
+            .is_none()
+    {
+        return Err(BinConfigError::TimeOverflow);
+    }
     let importation_dist = Uniform::from(Time::default()..=last_case);
     for _ in 0..sim_config.n_background {
         let imported_at = importation_dist.sample(&mut rng);
         let generation_time = imported_at + sim_config.time_to_background_mrca;
-        let genome = ancestral_genome.mutate_time(generation_time, mutation_rate, &mut rng);
+        let genome =
+            ancestral_genome.mutate_time(generation_time, mutation_rate.mean_rate(), &mut rng);
 
         let (_, history) = disease_model
             .generate_singleton(&mut rng)
@@ -135,11 +392,14 @@ where
         outbreak.source.push(None);
         outbreak.history.push(history);
         outbreak.genome.push(genome);
+        outbreak
+            .clock_rate
+            .push(mutation_rate.case_rate(None, &mut rng));
+        outbreak.setting.push(None);
+        outbreak.region.push(None);
     }
-
-    outbreak.rezero_time();
-
-    Ok(outbreak)
+    outbreak.sample_genomes(&mut rng);
+    Ok(())
 }
 
 fn accept(size_counts: &mut [Count], size_bin: Option<usize>) -> bool {
@@ -153,16 +413,43 @@ fn accept(size_counts: &mut [Count], size_bin: Option<usize>) -> bool {
 }
 
 impl BinnedOutbreakConfig {
-    fn validate(&self) {
-        let n = self.size_bin_edges.len();
-        assert_eq!(self.size_counts.len(), n - 1);
-        for i in 0..(n - 1) {
-            assert!(self.size_bin_edges[i + 1] > self.size_bin_edges[i]);
+    /// Check that the configuration can be simulated.
+    ///
+    /// This is called by [`binned_outbreaks`], but can be used to check a configuration before
+    /// starting any simulations. The background singletons can be imported after the index case
+    /// window, up to the end of the simulated outbreaks, so [`BinConfigError::TimeOverflow`] can
+    /// still be returned by [`binned_outbreaks`] when their time to the MRCA is close to the
+    /// largest representable time.
+    pub fn validate(&self) -> Result<(), BinConfigError> {
+        let edges = self.size_bin_edges.len();
+        if edges < 2 {
+            return Err(BinConfigError::EmptyEdges);
+        }
+        if self.size_counts.len() != edges - 1 {
+            return Err(BinConfigError::MismatchedCounts {
+                edges,
+                counts: self.size_counts.len(),
+            });
+        }
+        if let Some(i) = self.size_bin_edges.windows(2).position(|x| x[1] <= x[0]) {
+            return Err(BinConfigError::NonIncreasingEdges { index: i + 1 });
         }
+        if self.bad_simulation_cap == 0 {
+            return Err(BinConfigError::ZeroSimulationCap);
+        }
+        let latest_ancestry = self.time_to_mrca.max(self.time_to_background_mrca);
+        if self
+            .latest_importation
+            .checked_add(latest_ancestry)
+            .is_none()
+        {
+            return Err(BinConfigError::TimeOverflow);
+        }
+        Ok(())
     }
 
     fn max_size(&self) -> u32 {
-        *self.size_bin_edges.last().unwrap()
+        self.size_bin_edges.last().copied().unwrap_or_default()
     }
 
     fn size_bin(&self, n_cases: Count) -> Option<usize> {
@@ -194,6 +481,113 @@ impl BinnedOutbreakConfig {
     }
 }
 
+impl BinError {
+    /// The configuration that was simulated.
+    #[inline]
+    pub fn config(&self) -> &BinnedOutbreakConfig {
+        &self.config
+    }
+
+    /// Number of outbreaks requested in each size bin.
+    #[inline]
+    pub fn configured(&self) -> &[Count] {
+        &self.config.size_counts
+    }
+
+    /// Number of outbreaks still needed in each size bin.
+    #[inline]
+    pub fn remaining(&self) -> &[Count] {
+        &self.remaining
+    }
+
+    /// Number of cases in each rejected outbreak, in the order they were simulated.
+    #[inline]
+    pub fn discarded(&self) -> &[usize] {
+        &self.discarded
+    }
+
+    /// Number of rejected outbreaks that fell into each size bin after it was already full.
+    pub fn rejected(&self) -> Vec<usize> {
+        let mut counts = self.counts_with_margins();
+        counts.pop();
+        counts.remove(0);
+        counts
+    }
+
+    /// Number of rejected outbreaks no larger than the lowest bin edge.
+    pub fn underflow(&self) -> usize {
+        self.counts_with_margins()[0]
+    }
+
+    /// Number of rejected outbreaks larger than the highest bin edge.
+    ///
+    /// These include outbreaks that were stopped early for exceeding the highest edge.
+    pub fn overflow(&self) -> usize {
+        *self.counts_with_margins().last().unwrap()
+    }
+
+    /// Suggest what to change to fill the remaining bins.
+    ///
+    /// This compares the rejected outbreaks that were too small for any of the bins still to be
+    /// filled with those that were too large for them.
+    pub fn diagnosis(&self) -> Diagnosis {
+        let edges = &self.config.size_bin_edges;
+        let unfilled: Vec<usize> = (0..self.remaining.len())
+            .filter(|i| self.remaining[*i] > 0)
+            .collect();
+        let (Some(first), Some(last)) = (unfilled.first(), unfilled.last()) else {
+            return Diagnosis::NarrowBins;
+        };
+        let (lower, upper) = (edges[*first] as usize, edges[*last + 1] as usize);
+        let too_small = self.discarded.iter().filter(|n| **n <= lower).count();
+        let too_large = self.discarded.iter().filter(|n| **n > upper).count();
+        match too_small.cmp(&too_large) {
+            Ordering::Greater => Diagnosis::TooSmall,
+            Ordering::Less => Diagnosis::TooLarge,
+            Ordering::Equal => Diagnosis::NarrowBins,
+        }
+    }
+
+    /// Rejected outbreaks in the underflow, each size bin, and the overflow.
+    fn counts_with_margins(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.config.size_bin_edges.len() + 1];
+        for n in &self.discarded {
+            counts[self.config.size_bin_with_margins(*n as Count)] += 1;
+        }
+        counts
+    }
+}
+
+/// Likely reason that the size bins of a [`BinError`] were not filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnosis {
+    /// Most rejected outbreaks were smaller than the bins still to be filled.
+    TooSmall,
+
+    /// Most rejected outbreaks were larger than the bins still to be filled.
+    TooLarge,
+
+    /// The rejected outbreaks were not mostly too small or too large, so the bins still to be
+    /// filled are rarely reached.
+    NarrowBins,
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Diagnosis::TooSmall => {
+                "outbreaks too small: consider raising R or lowering the bin edges"
+            }
+            Diagnosis::TooLarge => {
+                "outbreaks too large: consider lowering R or raising the bin edges"
+            }
+            Diagnosis::NarrowBins => {
+                "bins rarely reached: consider widening them or raising bad_simulation_cap"
+            }
+        })
+    }
+}
+
 impl fmt::Display for BinError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(
@@ -205,18 +599,7 @@ impl fmt::Display for BinError {
         let mut labels = self.config.size_bin_labels();
         labels.insert(0, "underflow".to_owned());
         labels.push("overflow".to_owned());
-
-        let failed_bins = self
-            .discarded
-            .iter()
-            .map(|n| self.config.size_bin_with_margins(*n as u32))
-            .fold(BTreeMap::new(), |mut acc, val| {
-                acc.entry(val).and_modify(|e| *e += 1).or_insert(1);
-                acc
-            });
-        let failed_bins: Vec<_> = (0..labels.len())
-            .map(|x| *failed_bins.get(&x).unwrap_or(&0))
-            .collect();
+        let failed_bins = self.counts_with_margins();
 
         writeln!(
             f,
@@ -239,7 +622,7 @@ impl fmt::Display for BinError {
             )?;
         }
 
-        Ok(())
+        write!(f, "suggestion: {}", self.diagnosis())
     }
 }
 
@@ -248,6 +631,7 @@ mod tests {
     use super::*;
     use crate::disease::simple::SimpleDisease;
     use crate::genome::simple::SimpleGenome;
+    use crate::schedule::Piecewise;
     use crate::simulate::rounded_poisson;
     use rand::SeedableRng;
     use rand_distr::Gamma;
@@ -271,7 +655,7 @@ mod tests {
             n_background: 5,
             bad_simulation_cap: 2000,
         };
-        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324 as u64);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
         let genome = SimpleGenome::<64>::default();
         let outbreaks = binned_outbreaks(genome, &dm, mutation_rate, &sim_cfg, &mut rng).unwrap();
 
@@ -280,4 +664,181 @@ mod tests {
             4 + 3 + 2 + 5
         );
     }
+
+    #[test]
+    fn test_transmission_schedule() {
+        let dm = SimpleDisease {
+            incubation_time: rounded_poisson(2.).unwrap(),
+            reporting_time: rounded_poisson(2.).unwrap(),
+            reproduction_number: Gamma::new(3.0, 0.75).unwrap(),
+            infectiousness: vec![0.4, 0.275, 0.175, 0.1, 0.04, 0.01],
+        };
+        let sim_cfg = BinnedOutbreakConfig {
+            size_bin_edges: vec![1, 3, 10],
+            size_counts: vec![3, 2],
+            latest_importation: 30,
+            time_to_mrca: 30,
+            time_to_background_mrca: 30,
+            n_background: 0,
+            bad_simulation_cap: 2000,
+        };
+        // transmission stops entirely at time 25 of the importation window
+        let lockdown = Piecewise::new(1.0).unwrap().then(25, 0.0).unwrap();
+        let latest_transmission = |transmission| {
+            let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+            let genome = SimpleGenome::<64>::default();
+            let outbreaks = binned_outbreaks_in(
+                genome,
+                &dm,
+                Unlimited,
+                transmission,
+                0.1,
+                &sim_cfg,
+                &mut rng,
+            )
+            .unwrap();
+            outbreaks
+                .sources()
+                .iter()
+                .zip(outbreaks.history())
+                .filter(|(source, _)| source.is_some())
+                .map(|(_, history)| history.infected)
+                .max()
+                .unwrap()
+        };
+
+        assert!(latest_transmission(lockdown) < 25);
+        assert!(latest_transmission(Piecewise::new(1.0).unwrap()) >= 25);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_binned_outbreaks() {
+        let dm = SimpleDisease {
+            incubation_time: rounded_poisson(2.).unwrap(),
+            reporting_time: rounded_poisson(2.).unwrap(),
+            reproduction_number: Gamma::new(1.5, 0.75).unwrap(),
+            infectiousness: vec![0.4, 0.275, 0.175, 0.1, 0.04, 0.01],
+        };
+        let sim_cfg = BinnedOutbreakConfig {
+            size_bin_edges: vec![3, 7, 20, 150],
+            size_counts: vec![4, 3, 2],
+            latest_importation: 30,
+            time_to_mrca: 30,
+            time_to_background_mrca: 30,
+            n_background: 5,
+            bad_simulation_cap: 2000,
+        };
+        let simulate = || {
+            let genome = SimpleGenome::<64>::default();
+            binned_outbreaks_par(genome, &dm, Unlimited, 1.0, 0.1, &sim_cfg, 89324).unwrap()
+        };
+
+        let outbreaks = simulate();
+        assert_eq!(
+            outbreaks.sources().len() - outbreaks.sources().iter().flatten().count(),
+            4 + 3 + 2 + 5
+        );
+
+        let single_thread = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(simulate);
+        assert_eq!(single_thread.sources(), outbreaks.sources());
+        assert_eq!(single_thread.sampled_genomes(), outbreaks.sampled_genomes());
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = BinnedOutbreakConfig {
+            size_bin_edges: vec![3, 7, 20],
+            size_counts: vec![4, 3],
+            latest_importation: 30,
+            time_to_mrca: 30,
+            time_to_background_mrca: 30,
+            n_background: 5,
+            bad_simulation_cap: 200,
+        };
+        assert_eq!(valid.validate(), Ok(()));
+
+        let mut config = valid.clone();
+        config.size_bin_edges = vec![3];
+        config.size_counts = vec![];
+        assert_eq!(config.validate(), Err(BinConfigError::EmptyEdges));
+
+        let mut config = valid.clone();
+        config.size_counts = vec![4, 3, 2];
+        let err = BinConfigError::MismatchedCounts {
+            edges: 3,
+            counts: 3,
+        };
+        assert_eq!(config.validate(), Err(err));
+
+        let mut config = valid.clone();
+        config.size_bin_edges = vec![3, 20, 20];
+        let err = BinConfigError::NonIncreasingEdges { index: 2 };
+        assert_eq!(config.validate(), Err(err));
+
+        let mut config = valid.clone();
+        config.bad_simulation_cap = 0;
+        assert_eq!(config.validate(), Err(BinConfigError::ZeroSimulationCap));
+
+        let mut config = valid.clone();
+        config.time_to_background_mrca = Time::MAX;
+        assert_eq!(config.validate(), Err(BinConfigError::TimeOverflow));
+
+        let dm = SimpleDisease {
+            incubation_time: rounded_poisson(2.).unwrap(),
+            reporting_time: rounded_poisson(2.).unwrap(),
+            reproduction_number: Gamma::new(1.5, 0.75).unwrap(),
+            infectiousness: vec![0.4, 0.275, 0.175, 0.1, 0.04, 0.01],
+        };
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89324_u64);
+        let genome = SimpleGenome::<64>::default();
+        let err = binned_outbreaks(genome, &dm, 0.1, &config, &mut rng).unwrap_err();
+        assert!(matches!(
+            err,
+            BinnedError::Config(BinConfigError::TimeOverflow)
+        ));
+
+        // background singletons are imported until the end of the outbreaks, after the window
+        let mut config = valid.clone();
+        config.latest_importation = 0;
+        config.time_to_background_mrca = Time::MAX;
+        assert_eq!(config.validate(), Ok(()));
+        let genome = SimpleGenome::<64>::default();
+        let err = binned_outbreaks(genome, &dm, 0.1, &config, &mut rng).unwrap_err();
+        assert!(matches!(
+            err,
+            BinnedError::Config(BinConfigError::TimeOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_bin_error() {
+        let config = BinnedOutbreakConfig {
+            size_bin_edges: vec![3, 7, 20],
+            size_counts: vec![4, 3],
+            latest_importation: 30,
+            time_to_mrca: 30,
+            time_to_background_mrca: 30,
+            n_background: 5,
+            bad_simulation_cap: 6,
+        };
+        let err = BinError {
+            discarded: vec![1, 2, 5, 3, 21, 1],
+            remaining: vec![0, 2],
+            config,
+        };
+        assert_eq!(err.configured(), [4, 3]);
+        assert_eq!(err.remaining(), [0, 2]);
+        assert_eq!(err.rejected(), [1, 0]);
+        assert_eq!(err.underflow(), 4);
+        assert_eq!(err.overflow(), 1);
+        assert_eq!(err.diagnosis(), Diagnosis::TooSmall);
+        assert!(err.to_string().ends_with(
+            "suggestion: outbreaks too small: consider raising R or lowering the bin edges"
+        ));
+    }
 }
@@ -0,0 +1,170 @@
+use rand::Rng;
+use rand_distr::{Distribution, Poisson};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use super::{GrowthError, Outbreak};
+use crate::clock::MolecularClock;
+use crate::disease::ContinuousDiseaseModel;
+use crate::genome::Genome;
+use crate::population::Setting;
+use crate::Count;
+
+/// A transmission that has been scheduled but not yet simulated.
+#[derive(Debug, PartialEq)]
+struct Infection {
+    time: f64,
+    infector: Option<Count>,
+}
+
+impl Eq for Infection {}
+
+impl Ord for Infection {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time
+            .total_cmp(&other.time)
+            .then(self.infector.cmp(&other.infector))
+    }
+}
+
+impl PartialOrd for Infection {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Simulate an outbreak from one index genome in continuous time.
+///
+/// This is an event-driven alternative to [`simulate_outbreak`](super::simulate_outbreak). When
+/// each case is infected, the number of cases it goes on to infect is drawn from a Poisson
+/// distribution with mean equal to the area under its infectiousness curve, and the time of each
+/// of those infections is drawn from the curve itself. Infections are then simulated in order of
+/// time, so cases are numbered in the order they were infected and all times are fractional.
+///
+/// Genomes mutate over the exact time between infections with
+/// [`Genome::mutate_interval`]. Everyone is equally likely to be infected and nobody becomes
+/// immune, as for an [`Unlimited`](crate::population::Unlimited) population.
+///
+/// The simulation stops with `Ok()` once there are no infections left to simulate, or with
+/// `Err()` as soon as the size exceeds `max_size` cases.
+///
+/// # Panics
+/// If the disease model generates a case whose infectiousness is not in order of time, or has
+/// times or rates that are not finite, or negative rates.
+pub fn simulate_continuous<D, G, C, R>(
+    index_genome: G,
+    disease_model: &D,
+    mutation_rate: C,
+    max_size: Count,
+    mut rng: R,
+) -> Result<Outbreak<G, f64>, GrowthError<G, f64>>
+where
+    D: ContinuousDiseaseModel,
+    G: Genome,
+    C: MolecularClock,
+    R: Rng,
+{
+    let mut dm_state = D::State::default();
+    let mut outbreak: Outbreak<G, f64> = Outbreak::empty();
+
+    let mut index_genome = Some(index_genome);
+    let mut pending = BinaryHeap::from([Reverse(Infection {
+        time: 0.0,
+        infector: None,
+    })]);
+    while let Some(Reverse(Infection { time, infector })) = pending.pop() {
+        let (genome, rate) = match infector {
+            None => {
+                let rate = mutation_rate.case_rate(None, &mut rng);
+                (index_genome.take().unwrap(), rate)
+            }
+            Some(infector) => {
+                let infector_rate = outbreak.clock_rate[infector as usize];
+                let generation_time = time - outbreak.history[infector as usize].infected;
+                let genome = outbreak.genome[infector as usize]
+                    .mutate_interval(generation_time, infector_rate, &mut rng)
+                    .transmit(&mut rng);
+                let rate = mutation_rate.case_rate(Some(infector_rate), &mut rng);
+                (genome, rate)
+            }
+        };
+
+        let case = disease_model.generate_case(&mut dm_state, &mut rng);
+        assert!(
+            case.is_valid(),
+            "infectiousness must be in order of time, with finite times and non-negative rates"
+        );
+        let reproduction_number = case.reproduction_number();
+        if reproduction_number > 0.0 {
+            let n_infections = Poisson::new(reproduction_number).unwrap().sample(&mut rng) as Count;
+            let id = outbreak.n_cases() as Count;
+            for _ in 0..n_infections {
+                pending.push(Reverse(Infection {
+                    time: time + case.sample_infection(&mut rng),
+                    infector: Some(id),
+                }));
+            }
+        }
+
+        let mut history = case.into_history();
+        history.time_shift_forward(time);
+        outbreak.source.push(infector);
+        outbreak.history.push(history);
+        outbreak.genome.push(genome);
+        outbreak.clock_rate.push(rate);
+        outbreak.setting.push(infector.map(|_| Setting::Community));
+        outbreak.region.push(None);
+
+        if outbreak.n_cases() as Count > max_size {
+            outbreak.sample_genomes(&mut rng);
+            return Err(GrowthError {
+                outbreak: Box::new(outbreak),
+                max_size,
+            });
+        }
+    }
+
+    outbreak.sample_genomes(&mut rng);
+    Ok(outbreak)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disease::continuous::ContinuousDisease;
+    use crate::genome::simple::SimpleGenome;
+    use rand::SeedableRng;
+    use rand_distr::{Exp, Gamma};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    #[test]
+    fn test_continuous_outbreak() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+        let dm = ContinuousDisease {
+            incubation_time: Gamma::new(4.0, 0.25).unwrap(),
+            reporting_time: Exp::new(1.0).unwrap(),
+            reproduction_number: Gamma::new(3.0, 0.5).unwrap(),
+            infectiousness: vec![(0.0, 0.0), (0.5, 1.0), (2.0, 0.0)],
+        };
+        let genome = SimpleGenome::<256>::default();
+        let outbreak = simulate_continuous(genome, &dm, 1.0, 200, &mut rng);
+        let outbreak = outbreak.unwrap_or_else(|err| *err.outbreak);
+
+        assert!(outbreak.n_cases() > 1);
+        assert_eq!(outbreak.sampled_genomes().len(), outbreak.n_cases());
+        let infected: Vec<f64> = outbreak.history().iter().map(|x| x.infected).collect();
+        assert!(infected.windows(2).all(|x| x[0] <= x[1]));
+        assert!(infected.iter().any(|t| t.fract() != 0.0));
+        for (i, source) in outbreak.sources().iter().enumerate() {
+            if let Some(source) = source {
+                let infector = &outbreak.history()[*source as usize];
+                assert!(infected[i] >= infector.infectious_onset);
+                assert!(infected[i] <= infector.recovered);
+            }
+        }
+
+        let mut csv = Vec::new();
+        outbreak.write_csv(&mut csv).unwrap();
+        assert!(String::from_utf8(csv).unwrap().starts_with("case,parent"));
+    }
+}
@@ -0,0 +1,149 @@
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use rayon::prelude::*;
+use std::sync::mpsc;
+use std::thread;
+
+/// Independent random number generators for each replicate of an ensemble.
+///
+/// The first generator is seeded from `master_seed`, and each following one is advanced by a
+/// [`jump`](Xoshiro256PlusPlus::jump) of 2^128 steps, so the streams never overlap in practice.
+/// Replicate `i` always gets the same stream for the same master seed.
+pub fn replicate_rngs(master_seed: u64) -> impl Iterator<Item = Xoshiro256PlusPlus> {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(master_seed);
+    std::iter::repeat_with(move || {
+        let replicate = rng.clone();
+        rng.jump();
+        replicate
+    })
+}
+
+/// Run replicates of a simulation in parallel.
+///
+/// `simulate` is called once for each of `n_replicates` replicates with the random number
+/// generator from [`replicate_rngs`], typically to call
+/// [`simulate_outbreak`](crate::simulate_outbreak) or
+/// [`binned_outbreaks`](super::binned_outbreaks). The results are returned in order of replicate
+/// and are identical for the same `master_seed`, however many threads [`rayon`] uses.
+///
+/// ```
+/// use obsim::simulate::{ensemble, rounded_poisson, simulate_outbreak};
+/// use obsim::simple::{SimpleDisease, SimpleGenome};
+/// use rand_distr::Gamma;
+///
+/// let disease_model = SimpleDisease {
+///     incubation_time: rounded_poisson(1.).unwrap(),
+///     reporting_time: rounded_poisson(1.).unwrap(),
+///     reproduction_number: Gamma::new(1.5, 0.75).unwrap(),
+///     infectiousness: vec![0.34, 0.33, 0.33],
+/// };
+/// let outbreaks = ensemble(893924, 100, |rng| {
+///     let genome = SimpleGenome::<64>::default();
+///     simulate_outbreak(genome, &disease_model, 0.01, 100, rng)
+/// });
+/// assert_eq!(outbreaks.len(), 100);
+/// ```
+pub fn ensemble<T, F>(master_seed: u64, n_replicates: usize, simulate: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(Xoshiro256PlusPlus) -> T + Sync,
+{
+    let rngs: Vec<_> = replicate_rngs(master_seed).take(n_replicates).collect();
+    rngs.into_par_iter().map(&simulate).collect()
+}
+
+/// Run replicates of a simulation in parallel, passing each result to `sink` as it finishes.
+///
+/// This is the same as [`ensemble`], except that the results do not all need to be kept in
+/// memory, e.g. when writing each outbreak to a file. `sink` is called on a separate thread with
+/// the replicate number and result of each replicate, in the order that they finish. Only about
+/// one finished result per thread waits for `sink` at any time, so a slow `sink` holds up the
+/// replicates rather than letting their results pile up.
+///
+/// The replicates run in the current [`rayon`] thread pool, so they can be limited with
+/// [`ThreadPool::install`](rayon::ThreadPool::install).
+pub fn ensemble_for_each<T, F, S>(master_seed: u64, n_replicates: usize, simulate: F, mut sink: S)
+where
+    T: Send,
+    F: Fn(Xoshiro256PlusPlus) -> T + Sync,
+    S: FnMut(usize, T) + Send,
+{
+    let rngs: Vec<_> = replicate_rngs(master_seed).take(n_replicates).collect();
+    let (sender, receiver) = mpsc::sync_channel(rayon::current_num_threads());
+    thread::scope(|scope| {
+        // the sink runs outside the pool, so the workers only wait for it when the channel is full
+        scope.spawn(move || {
+            for (i, result) in receiver {
+                sink(i, result);
+            }
+        });
+        rngs.into_par_iter()
+            .enumerate()
+            .for_each_with(sender, |sender, (i, rng)| {
+                sender.send((i, simulate(rng))).unwrap();
+            });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disease::simple::test_disease;
+    use crate::genome::simple::SimpleGenome;
+    use crate::simulate::simulate_outbreak;
+
+    #[test]
+    fn test_reproducible() {
+        let dm = test_disease(3.0, 0.5);
+        let simulate = |rng| {
+            let genome = SimpleGenome::<64>::default();
+            let outbreak = simulate_outbreak(genome, &dm, 0.1, 50, rng);
+            outbreak.unwrap_or_else(|err| *err.outbreak)
+        };
+
+        let outbreaks = ensemble(89324, 20, simulate);
+        let serial: Vec<_> = replicate_rngs(89324).take(20).map(simulate).collect();
+        let single_thread = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(|| ensemble(89324, 20, simulate));
+        for (a, b) in outbreaks
+            .iter()
+            .zip(&serial)
+            .chain(outbreaks.iter().zip(&single_thread))
+        {
+            assert_eq!(a.sources(), b.sources());
+            assert_eq!(a.sampled_genomes(), b.sampled_genomes());
+        }
+        assert!(outbreaks
+            .iter()
+            .any(|x| x.n_cases() != outbreaks[0].n_cases()));
+
+        let mut streamed = vec![None; 20];
+        ensemble_for_each(89324, 20, simulate, |i, outbreak| {
+            streamed[i] = Some(outbreak.n_cases());
+        });
+        let sizes: Vec<_> = outbreaks.iter().map(|x| Some(x.n_cases())).collect();
+        assert_eq!(streamed, sizes);
+
+        // the replicates run in the caller's pool, and the channel fills up while the sink is busy
+        let mut streamed = vec![None; 20];
+        let single_thread = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .thread_name(|_| "single".to_owned())
+            .build()
+            .unwrap();
+        let in_pool = |rng| {
+            assert_eq!(thread::current().name(), Some("single"));
+            simulate(rng)
+        };
+        single_thread.install(|| {
+            ensemble_for_each(89324, 20, in_pool, |i, outbreak| {
+                thread::sleep(std::time::Duration::from_millis(1));
+                streamed[i] = Some(outbreak.n_cases());
+            })
+        });
+        assert_eq!(streamed, sizes);
+    }
+}
@@ -0,0 +1,128 @@
+//! Tabular export of case data.
+
+use super::outbreak::{case_label, get_cluster_ids, Outbreak};
+use crate::case::Timestamp;
+use std::fmt::Display;
+use std::io;
+
+const COLUMNS: &[&str] = &[
+    "case",
+    "parent",
+    "outbreak",
+    "setting",
+    "region",
+    "infected",
+    "infectious_onset",
+    "infectious_peak",
+    "recovered",
+    "symptom_onset",
+    "reported",
+    "sampled",
+    "clock_rate",
+];
+
+impl<G, T: Timestamp> Outbreak<G, T> {
+    /// Print a line list of all cases as comma-separated values.
+    ///
+    /// There is one row per case, with a header row naming the columns:
+    ///
+    /// * `case`: the case label, which is also the sequence identifier in
+    ///   [`write_fasta`](Outbreak::write_fasta)
+    /// * `parent`: the label of the infector, if any
+    /// * `outbreak`: the outbreak number, see [`outbreaks`](Outbreak::outbreaks)
+    /// * `setting`: where the case was infected, see [`settings`](Outbreak::settings)
+    /// * `region`: the region of the case, see [`regions`](Outbreak::regions)
+    /// * `infected`, `infectious_onset`, `infectious_peak`, `recovered`, `symptom_onset`,
+    ///   `reported`, `sampled`: the disease history, see [`History`](crate::case::History)
+    /// * `clock_rate`: the mutation rate of the case
+    ///
+    /// Missing values are left empty.
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        self.write_line_list(writer, ',')
+    }
+
+    /// Print a line list of all cases as tab-separated values.
+    ///
+    /// The columns are the same as for [`write_csv`](Outbreak::write_csv).
+    pub fn write_tsv<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        self.write_line_list(writer, '\t')
+    }
+
+    fn write_line_list<W: io::Write>(&self, mut writer: W, delimiter: char) -> io::Result<()> {
+        let outbreaks = get_cluster_ids(&self.source);
+
+        writeln!(writer, "{}", COLUMNS.join(&delimiter.to_string()))?;
+        for (i, history) in self.history.iter().enumerate() {
+            let row = [
+                case_label(i),
+                optional(self.source[i].map(|x| case_label(x as usize))),
+                outbreaks[i].to_string(),
+                optional(self.setting[i]),
+                optional(self.region[i].as_ref()),
+                history.infected.to_string(),
+                history.infectious_onset.to_string(),
+                history.infectious_peak.to_string(),
+                history.recovered.to_string(),
+                optional(history.symptom_onset),
+                optional(history.reported),
+                optional(history.sampled),
+                self.clock_rate[i].to_string(),
+            ];
+            writeln!(writer, "{}", row.join(&delimiter.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+fn optional<T: Display>(value: Option<T>) -> String {
+    value.map(|x| x.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::case::History;
+    use crate::population::Setting;
+    use crate::simulate::Outbreak;
+
+    #[test]
+    fn test_csv() {
+        let outbreak = Outbreak {
+            source: vec![None, Some(0)],
+            history: vec![
+                History {
+                    infected: 0,
+                    infectious_onset: 1,
+                    infectious_peak: 2,
+                    recovered: 4,
+                    reported: Some(3),
+                    symptom_onset: Some(1),
+                    sampled: Some(3),
+                },
+                History {
+                    infected: 2,
+                    infectious_onset: 3,
+                    infectious_peak: 3,
+                    recovered: 5,
+                    reported: None,
+                    symptom_onset: None,
+                    sampled: None,
+                },
+            ],
+            genome: vec![(); 2],
+            sampled_genome: vec![(); 2],
+            clock_rate: vec![0.5; 2],
+            setting: vec![None, Some(Setting::Household)],
+            region: vec![None, Some("north".into())],
+        };
+
+        let mut out = Vec::new();
+        outbreak.write_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "case,parent,outbreak,setting,region,infected,infectious_onset,infectious_peak,recovered,\
+             symptom_onset,reported,sampled,clock_rate\n\
+             case000000,,0,,,0,1,2,4,1,3,3,0.5\n\
+             case000001,case000000,0,household,north,2,3,3,5,,,,0.5\n"
+        );
+    }
+}
@@ -1,18 +1,34 @@
 //! Outbreak simulation.
 
-use rand::distributions::{DistMap, WeightedIndex};
+use rand::distributions::DistMap;
 use rand::Rng;
 use rand_distr::{Distribution, Poisson, PoissonError};
 use thiserror::Error;
 
 use crate::case::Case;
+use crate::clock::MolecularClock;
 use crate::disease::DiseaseModel;
 use crate::genome::Genome;
+use crate::population::{Infection, Population, Unlimited};
+use crate::schedule::TransmissionSchedule;
 use crate::{Count, Time};
 
 mod binned;
+mod continuous;
+#[cfg(feature = "rayon")]
+mod ensemble;
+mod line_list;
 pub(super) mod outbreak;
-pub use binned::{binned_outbreaks, BinError, BinnedOutbreakConfig};
+mod tree;
+#[cfg(feature = "rayon")]
+pub use binned::binned_outbreaks_par;
+pub use binned::{
+    binned_outbreaks, binned_outbreaks_in, BinConfigError, BinError, BinnedError,
+    BinnedOutbreakConfig, Diagnosis,
+};
+pub use continuous::simulate_continuous;
+#[cfg(feature = "rayon")]
+pub use ensemble::{ensemble, ensemble_for_each, replicate_rngs};
 use outbreak::Outbreak;
 
 /// See [`rounded_poisson`].
@@ -31,8 +47,9 @@ fn round_time(t: f64) -> Time {
 
 #[derive(Error, Debug)]
 #[error("outbreak exceeded {max_size} cases after time step")]
-pub struct GrowthError<G> {
-    pub outbreak: Outbreak<G>,
+pub struct GrowthError<G, T = Time> {
+    /// The outbreak up to the time step where it exceeded `max_size`.
+    pub outbreak: Box<Outbreak<G, T>>,
     pub max_size: Count,
 }
 
@@ -42,19 +59,71 @@ pub struct GrowthError<G> {
 /// when there are no infectious cases left. These two cases are distinguished
 /// by the result: `Ok()` indicates full recovery, and `Err()` indicates
 /// termination due to `max_size`.
-pub fn simulate_outbreak<D, G, R>(
+///
+/// `mutation_rate` is the expected number of mutations per unit time. Give an `f64` for a strict
+/// clock, or any other [`MolecularClock`] to vary the rate between cases.
+///
+/// Each case receives a genome when it is infected, and its genome continues to evolve until it
+/// is sampled. See [`Outbreak::genomes`] and [`Outbreak::sampled_genomes`].
+///
+/// The outbreak spreads through an [`Unlimited`] population; see [`simulate_outbreak_in`] for
+/// other populations.
+pub fn simulate_outbreak<D, G, C, R>(
+    index_genome: G,
+    disease_model: &D,
+    mutation_rate: C,
+    max_size: Count,
+    rng: R,
+) -> Result<Outbreak<G>, GrowthError<G>>
+where
+    D: DiseaseModel,
+    G: Genome,
+    C: MolecularClock,
+    R: Rng,
+{
+    simulate_outbreak_in(
+        index_genome,
+        disease_model,
+        Unlimited,
+        1.0,
+        mutation_rate,
+        max_size,
+        rng,
+    )
+}
+
+/// Simulate an outbreak from one index genome spreading through a population.
+///
+/// This is the same as [`simulate_outbreak`], except that the `population` determines the new
+/// infections in each time step. With a finite population such as
+/// [`WellMixed`](crate::population::WellMixed), outbreaks end once the susceptibles are depleted,
+/// so `max_size` can be set to `Count::MAX` to always simulate to completion.
+///
+/// The infectivity of every case is multiplied by the `transmission` schedule at each time step,
+/// with the index case infected at time zero. Give `1.0` for no change in transmission over time,
+/// or see [`schedule`](crate::schedule) for interventions.
+///
+/// # Panics
+/// If the `transmission` schedule gives a multiplier that is negative or not finite.
+pub fn simulate_outbreak_in<D, P, S, G, C, R>(
     index_genome: G,
     disease_model: &D,
-    mutation_rate: f64,
+    population: P,
+    transmission: S,
+    mutation_rate: C,
     max_size: Count,
     mut rng: R,
 ) -> Result<Outbreak<G>, GrowthError<G>>
 where
     D: DiseaseModel,
+    P: Population,
+    S: TransmissionSchedule,
     G: Genome,
+    C: MolecularClock,
     R: Rng,
 {
     let mut dm_state = D::State::default();
+    let mut pop_state = population.start(&mut rng);
 
     // start with the index case
     let (index, history) = disease_model
@@ -66,54 +135,63 @@ where
         source: vec![None],
         history: vec![history],
         genome: vec![index_genome],
+        sampled_genome: Vec::new(),
+        clock_rate: vec![mutation_rate.case_rate(None, &mut rng)],
+        setting: vec![None],
+        region: vec![population.region(&pop_state, 0)],
     };
 
     loop {
-        let case_infectivity: Vec<f64> = cases.iter_mut().map(Case::step).collect();
-        let total_infectivity: f64 = case_infectivity.iter().sum();
-
-        if total_infectivity > 0.0 {
-            let case_dist = Poisson::new(total_infectivity).unwrap();
-            let new_cases = case_dist.sample(&mut rng) as Count;
-
-            if new_cases > 0 {
-                let infector_dist = WeightedIndex::new(case_infectivity).unwrap();
-
-                outbreak.source.reserve(new_cases as usize);
-                outbreak.history.reserve(new_cases as usize);
-                outbreak.genome.reserve(new_cases as usize);
-
-                for _ in 0..new_cases {
-                    let infector = infector_dist.sample(&mut rng) as Count;
-                    outbreak.source.push(Some(infector));
-                    let (case, mut history) = disease_model
-                        .generate_case(&mut dm_state, &mut rng)
-                        .into_case_history();
-                    cases.push(case);
-                    history.time_shift_forward(t);
-                    outbreak.history.push(history);
-
-                    let generation_time = t - outbreak.history[infector as usize].infected;
-                    let new_genome = if generation_time < 1 {
-                        outbreak.genome[infector as usize].clone()
-                    } else {
-                        outbreak.genome[infector as usize].mutate_time(
-                            generation_time,
-                            mutation_rate,
-                            &mut rng,
-                        )
-                    };
-                    outbreak.genome.push(new_genome);
-                }
-            }
+        let multiplier = transmission.multiplier(t);
+        let case_infectivity: Vec<f64> = cases.iter_mut().map(|x| multiplier * x.step()).collect();
+        let new_cases = population.transmit(&mut pop_state, &case_infectivity, &mut rng);
+
+        outbreak.source.reserve(new_cases as usize);
+        outbreak.history.reserve(new_cases as usize);
+        outbreak.genome.reserve(new_cases as usize);
+        outbreak.clock_rate.reserve(new_cases as usize);
+        outbreak.setting.reserve(new_cases as usize);
+        outbreak.region.reserve(new_cases as usize);
+
+        for _ in 0..new_cases {
+            let Infection { infector, setting } =
+                population.next_infection(&mut pop_state, &mut rng);
+            outbreak.source.push(Some(infector));
+            outbreak.setting.push(Some(setting));
+            let region = population.region(&pop_state, outbreak.source.len() as Count - 1);
+            outbreak.region.push(region);
+            let (case, mut history) = disease_model
+                .generate_case(&mut dm_state, &mut rng)
+                .into_case_history();
+            cases.push(case);
+            history.time_shift_forward(t);
+            outbreak.history.push(history);
+
+            let infector_rate = outbreak.clock_rate[infector as usize];
+            let generation_time = t - outbreak.history[infector as usize].infected;
+            let new_genome = if generation_time < 1 {
+                outbreak.genome[infector as usize].transmit(&mut rng)
+            } else {
+                outbreak.genome[infector as usize]
+                    .mutate_time(generation_time, infector_rate, &mut rng)
+                    .transmit(&mut rng)
+            };
+            outbreak.genome.push(new_genome);
+            let rate = mutation_rate.case_rate(Some(infector_rate), &mut rng);
+            outbreak.clock_rate.push(rate);
         }
 
         if cases.iter().all(Case::is_recovered) {
+            outbreak.sample_genomes(&mut rng);
             return Ok(outbreak);
         }
 
         if outbreak.n_cases() as Count > max_size {
-            return Err(GrowthError { outbreak, max_size });
+            outbreak.sample_genomes(&mut rng);
+            return Err(GrowthError {
+                outbreak: Box::new(outbreak),
+                max_size,
+            });
         }
 
         t += 1;
@@ -123,8 +201,10 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::disease::simple::SimpleDisease;
+    use crate::clock::RelaxedClock;
+    use crate::disease::simple::{test_disease, SimpleDisease};
     use crate::genome::simple::SimpleGenome;
+    use crate::population::WellMixed;
     use crate::simulate::rounded_poisson;
     use rand::SeedableRng;
     use rand_distr::Gamma;
@@ -132,7 +212,7 @@ mod tests {
 
     #[test]
     fn test_simple_outbreak_simulation() {
-        let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924 as u64);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
         let dm = SimpleDisease {
             incubation_time: rounded_poisson(1.).unwrap(),
             reporting_time: rounded_poisson(1.).unwrap(),
@@ -148,4 +228,106 @@ mod tests {
         let outbreak = outbreak.unwrap();
         assert_eq!(outbreak.n_cases(), 5)
     }
+
+    #[test]
+    fn test_finite_population() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+        let dm = test_disease(30.0, 0.1);
+        let population = WellMixed { size: 200 };
+        let genome = SimpleGenome::<64>::default();
+        let outbreak =
+            simulate_outbreak_in(genome, &dm, population, 1.0, 0.1, Count::MAX, &mut rng);
+
+        let outbreak = outbreak.unwrap();
+        assert!(outbreak.n_cases() > 100);
+        assert!(outbreak.n_cases() <= 200);
+    }
+
+    #[test]
+    fn test_transmission_schedule() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+        let dm = test_disease(50.0, 0.1);
+        let genome = SimpleGenome::<64>::default();
+        let outbreak = simulate_outbreak_in(genome, &dm, Unlimited, 0.0, 0.1, 100, &mut rng);
+        assert_eq!(outbreak.unwrap().n_cases(), 1);
+
+        let lockdown = |t| if t < 5 { 1.0 } else { 0.0 };
+        let genome = SimpleGenome::<64>::default();
+        let outbreak = simulate_outbreak_in(genome, &dm, Unlimited, lockdown, 0.1, 1000, &mut rng);
+        let outbreak = outbreak.unwrap();
+        assert!(outbreak.n_cases() > 1);
+        assert!(outbreak.history().iter().all(|x| x.infected < 5));
+    }
+
+    #[test]
+    fn test_relaxed_clock() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+        let dm = test_disease(3.0, 0.75);
+        let clock = RelaxedClock::lognormal(0.1, 0.5).unwrap().autocorrelated();
+        let genome = SimpleGenome::<64>::default();
+        let outbreak = simulate_outbreak(genome, &dm, clock, 50, &mut rng);
+        let outbreak = outbreak.unwrap_or_else(|err| *err.outbreak);
+
+        let rates = outbreak.clock_rates();
+        assert_eq!(rates.len(), outbreak.n_cases());
+        assert!(rates.windows(2).any(|x| x[0] != x[1]));
+
+        let outbreak = simulate_outbreak(SimpleGenome::<64>::default(), &dm, 0.1, 50, &mut rng);
+        let outbreak = outbreak.unwrap_or_else(|err| *err.outbreak);
+        assert!(outbreak.clock_rates().iter().all(|x| *x == 0.1));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+        let dm = test_disease(3.0, 0.75);
+        let genome = SimpleGenome::<64>::default();
+        let outbreak = simulate_outbreak(genome, &dm, 1.0, 50, &mut rng);
+        let outbreak = outbreak.unwrap_or_else(|err| *err.outbreak);
+
+        let json = serde_json::to_string(&outbreak).unwrap();
+        let restored: Outbreak<SimpleGenome<64>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.sources(), outbreak.sources());
+        assert_eq!(restored.sampled_genomes(), outbreak.sampled_genomes());
+        assert_eq!(restored.end_time(), outbreak.end_time());
+
+        let bad_json = json.replacen("null", "5", 1);
+        assert!(serde_json::from_str::<Outbreak<SimpleGenome<64>>>(&bad_json).is_err());
+        assert!(serde_json::from_str::<Outbreak<SimpleGenome<32>>>(&json).is_err());
+
+        use crate::genome::nucleotide::NucleotideGenome;
+        use crate::genome::sparse::SparseGenome;
+        use crate::genome::substitution::Jc69;
+
+        let genome = SparseGenome::from(NucleotideGenome::random(1000, &Jc69, &mut rng));
+        let outbreak = simulate_outbreak(genome, &dm, 1.0, 50, &mut rng);
+        let outbreak = outbreak.unwrap_or_else(|err| *err.outbreak);
+        assert!(outbreak.n_cases() > 1);
+
+        let json = serde_json::to_string(&outbreak).unwrap();
+        assert_eq!(json.matches("\"sequence\"").count(), 1);
+        let restored: Outbreak<SparseGenome> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.sampled_genomes(), outbreak.sampled_genomes());
+    }
+
+    #[test]
+    fn test_sampled_genomes() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(893924_u64);
+        let dm = SimpleDisease {
+            reporting_time: rounded_poisson(10.).unwrap(),
+            ..test_disease(3.0, 0.75)
+        };
+        let genome = SimpleGenome::<256>::default();
+        let outbreak = simulate_outbreak(genome, &dm, 1.0, 50, &mut rng);
+        let outbreak = outbreak.unwrap_or_else(|err| *err.outbreak);
+
+        assert_eq!(outbreak.sampled_genomes().len(), outbreak.n_cases());
+        for (i, history) in outbreak.history().iter().enumerate() {
+            assert_eq!(history.sampled, history.reported);
+            let transmitted = &outbreak.genomes()[i];
+            let sampled = &outbreak.sampled_genomes()[i];
+            assert!(transmitted.snps(sampled) > 0);
+        }
+    }
 }
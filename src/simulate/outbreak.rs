@@ -1,19 +1,142 @@
-use crate::case::History;
+use crate::case::{History, Timestamp};
+#[cfg(feature = "serde")]
+use crate::genome::sparse::share_references;
 use crate::genome::Genome;
+use crate::population::Setting;
 use crate::{Count, Time};
+use rand::Rng;
 use std::io;
+use std::sync::Arc;
 
 const FASTA_N_COLS: usize = 70;
 
 /// A simulated outbreak containing a number of cases.
+///
+/// Times are integer time steps, except for outbreaks simulated in continuous time by
+/// [`simulate_continuous`](super::simulate_continuous), which have `f64` times.
+///
+/// With the `serde` feature, outbreaks can be serialized and deserialized as long as the genome
+/// type also supports it.
 #[derive(Debug)]
-pub struct Outbreak<G> {
+pub struct Outbreak<G, T = Time> {
     pub(super) source: Vec<Option<Count>>,
-    pub(super) history: Vec<History>,
+    pub(super) history: Vec<History<T>>,
     pub(super) genome: Vec<G>,
+    pub(super) sampled_genome: Vec<G>,
+    pub(super) clock_rate: Vec<f64>,
+    pub(super) setting: Vec<Option<Setting>>,
+    pub(super) region: Vec<Option<Arc<str>>>,
 }
 
-impl<G: Genome> Outbreak<G> {
+/// Unvalidated form of [`Outbreak`] used for deserialization.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct OutbreakData<G, T> {
+    source: Vec<Option<Count>>,
+    history: Vec<History<T>>,
+    genome: Vec<G>,
+    sampled_genome: Vec<G>,
+    clock_rate: Vec<f64>,
+    setting: Vec<Option<Setting>>,
+    region: Vec<Option<Arc<str>>>,
+}
+
+/// Borrowed form of [`Outbreak`] used for serialization.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct OutbreakRef<'a, G, T> {
+    source: &'a [Option<Count>],
+    history: &'a [History<T>],
+    genome: &'a [G],
+    sampled_genome: &'a [G],
+    clock_rate: &'a [f64],
+    setting: &'a [Option<Setting>],
+    region: &'a [Option<Arc<str>>],
+}
+
+// genomes that share a reference sequence only write it once per outbreak
+#[cfg(feature = "serde")]
+impl<G: serde::Serialize, T: serde::Serialize> serde::Serialize for Outbreak<G, T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = OutbreakRef {
+            source: &self.source,
+            history: &self.history,
+            genome: &self.genome,
+            sampled_genome: &self.sampled_genome,
+            clock_rate: &self.clock_rate,
+            setting: &self.setting,
+            region: &self.region,
+        };
+        share_references(|| data.serialize(serializer))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, G, T> serde::Deserialize<'de> for Outbreak<G, T>
+where
+    G: serde::Deserialize<'de>,
+    T: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = share_references(|| OutbreakData::deserialize(deserializer))?;
+        Outbreak::try_from(data).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<G, T> TryFrom<OutbreakData<G, T>> for Outbreak<G, T> {
+    type Error = String;
+
+    fn try_from(data: OutbreakData<G, T>) -> Result<Self, Self::Error> {
+        let n = data.source.len();
+        let lengths = [
+            data.history.len(),
+            data.genome.len(),
+            data.sampled_genome.len(),
+            data.clock_rate.len(),
+            data.setting.len(),
+            data.region.len(),
+        ];
+        if lengths.iter().any(|x| *x != n) {
+            return Err("outbreak must have the same number of entries for every field".to_owned());
+        }
+        for (i, source) in data.source.iter().enumerate() {
+            if source.is_some_and(|x| x as usize >= i) {
+                return Err(format!(
+                    "case {} must have an earlier case as its source",
+                    i
+                ));
+            }
+        }
+
+        Ok(Outbreak {
+            source: data.source,
+            history: data.history,
+            genome: data.genome,
+            sampled_genome: data.sampled_genome,
+            clock_rate: data.clock_rate,
+            setting: data.setting,
+            region: data.region,
+        })
+    }
+}
+
+impl<G, T> Outbreak<G, T> {
+    /// An outbreak with no cases, to be extended.
+    pub(super) fn empty() -> Self {
+        Outbreak {
+            source: Vec::new(),
+            history: Vec::new(),
+            genome: Vec::new(),
+            sampled_genome: Vec::new(),
+            clock_rate: Vec::new(),
+            setting: Vec::new(),
+            region: Vec::new(),
+        }
+    }
+}
+
+impl<G: Genome, T: Timestamp> Outbreak<G, T> {
     /// Get the source (infector) of all cases.
     ///
     /// The values are `None` when a case has no infector. This occurs for the index case in each
@@ -37,25 +160,65 @@ impl<G: Genome> Outbreak<G> {
     /// Get the disease history times of all cases.
     ///
     #[inline]
-    pub fn history(&self) -> &[History] {
+    pub fn history(&self) -> &[History<T>] {
         &self.history
     }
 
-    /// Get the genomes of all cases.
+    /// Get the genomes of all cases at the time they were infected.
+    ///
+    /// This is the genome that was transmitted to each case.
     #[inline]
     pub fn genomes(&self) -> &[G] {
         &self.genome
     }
 
-    /// Print a FASTA file representing the simulated genomes.
+    /// Get the genomes of all cases at the time they were sampled.
+    ///
+    /// These have accumulated mutations between infection and sampling, and are the sequences
+    /// that would be obtained from each case. Cases that were never sampled have their
+    /// transmitted genome.
+    #[inline]
+    pub fn sampled_genomes(&self) -> &[G] {
+        &self.sampled_genome
+    }
+
+    /// Get the mutation rate of all cases.
+    ///
+    /// These are all equal under a strict clock. See [`MolecularClock`](crate::clock::MolecularClock).
+    #[inline]
+    pub fn clock_rates(&self) -> &[f64] {
+        &self.clock_rate
+    }
+
+    /// Get the setting where each case was infected.
+    ///
+    /// The values are `None` for cases without an infector. See
+    /// [`Population`](crate::population::Population).
+    #[inline]
+    pub fn settings(&self) -> &[Option<Setting>] {
+        &self.setting
+    }
+
+    /// Get the region label of each case.
+    ///
+    /// The values are `None` unless the population has regions, such as a
+    /// [`Metapopulation`](crate::population::Metapopulation).
+    #[inline]
+    pub fn regions(&self) -> &[Option<Arc<str>>] {
+        &self.region
+    }
+
+    /// Print a FASTA file representing the sampled genomes.
+    ///
+    /// When a case has a region label, it is added to the end of the header as `region=`.
     pub fn write_fasta<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
         let sources = self.outbreaks();
 
         let mut sequence = Vec::<u8>::new();
-        for (i, genome) in self.genome.iter().enumerate() {
-            writeln!(
+        for (i, genome) in self.sampled_genome.iter().enumerate() {
+            write!(
                 writer,
-                ">case{:06} day_infected={} day_reported={} outbreak={} parent={}",
+                ">case{:06} day_infected={} day_reported={} outbreak={} parent={} day_sampled={}",
                 i,
                 self.history[i].infected,
                 self.history[i]
@@ -64,9 +227,17 @@ impl<G: Genome> Outbreak<G> {
                     .unwrap_or("".to_string()),
                 sources[i],
                 self.source[i]
-                    .map(|x| format!("case{:06}", x))
-                    .unwrap_or_else(String::new)
+                    .map(|x| case_label(x as usize))
+                    .unwrap_or_default(),
+                self.history[i]
+                    .sampled
+                    .map(|x| x.to_string())
+                    .unwrap_or("".to_string())
             )?;
+            if let Some(region) = &self.region[i] {
+                write!(writer, " region={}", region)?;
+            }
+            writeln!(writer)?;
 
             genome.write_nucleotides(&mut sequence)?;
             for chunk in sequence.chunks(FASTA_N_COLS) {
@@ -80,8 +251,12 @@ impl<G: Genome> Outbreak<G> {
 
     /// Modify all times such that the earliest infection occurs at time zero.
     pub fn rezero_time(&mut self) {
-        let start_time = self.history.iter().flat_map(|x| x.iter().min()).min();
-        let by_amount = start_time.unwrap_or(0);
+        let start_time =
+            self.history
+                .iter()
+                .flat_map(History::iter)
+                .reduce(|a, b| if b < a { b } else { a });
+        let by_amount = start_time.unwrap_or_default();
         for history in &mut self.history {
             history.time_shift_back(by_amount);
         }
@@ -89,8 +264,11 @@ impl<G: Genome> Outbreak<G> {
 
     /// The latest time stored in the outbreak metadata.
     #[inline]
-    pub fn end_time(&self) -> Option<Time> {
-        self.history.iter().flat_map(|x| x.iter().max()).max()
+    pub fn end_time(&self) -> Option<T> {
+        self.history
+            .iter()
+            .flat_map(History::iter)
+            .reduce(|a, b| if b > a { b } else { a })
     }
 
     /// The number of cases simulated.
@@ -100,7 +278,7 @@ impl<G: Genome> Outbreak<G> {
     }
 
     /// Increase all times in this outbreak by a fixed amount.
-    pub fn time_shift(&mut self, by_amount: Time) {
+    pub fn time_shift(&mut self, by_amount: T) {
         for history in &mut self.history {
             history.time_shift_forward(by_amount);
         }
@@ -113,12 +291,40 @@ impl<G: Genome> Outbreak<G> {
     }
 
     /// Append all cases from `other`, shifting their IDs to avoid collisions.
-    pub fn extend_with(&mut self, mut other: Outbreak<G>) {
+    pub fn extend_with(&mut self, mut other: Outbreak<G, T>) {
         other.id_shift(self.source.len() as Count);
         self.source.extend(other.source);
         self.history.extend(other.history);
         self.genome.extend(other.genome);
+        self.sampled_genome.extend(other.sampled_genome);
+        self.clock_rate.extend(other.clock_rate);
+        self.setting.extend(other.setting);
+        self.region.extend(other.region);
     }
+
+    /// Derive the sampled genomes of any cases that do not yet have one.
+    ///
+    /// Each transmitted genome is mutated from the time of infection up to the time of sampling.
+    pub(super) fn sample_genomes<R: Rng>(&mut self, mut rng: R) {
+        for i in self.sampled_genome.len()..self.genome.len() {
+            let history = &self.history[i];
+            let sampled = match history.sampled {
+                Some(t) if t > history.infected => T::mutate(
+                    &self.genome[i],
+                    t - history.infected,
+                    self.clock_rate[i],
+                    &mut rng,
+                ),
+                _ => self.genome[i].clone(),
+            };
+            self.sampled_genome.push(sampled);
+        }
+    }
+}
+
+/// Label of a case in output files.
+pub(super) fn case_label(id: usize) -> String {
+    format!("case{:06}", id)
 }
 
 /// Convert a vector of sources into a vector of cluster IDs.
@@ -0,0 +1,193 @@
+//! Conversion of transmission chains into time-scaled phylogenies.
+
+use super::outbreak::{case_label, get_cluster_ids, Outbreak};
+use crate::case::Timestamp;
+use std::io;
+
+/// A subtree in Newick format, together with the time of its root node or tip.
+struct Subtree<T> {
+    newick: String,
+    time: T,
+}
+
+impl<T: Timestamp> Subtree<T> {
+    /// Format the subtree as a child of a node at `parent_time`.
+    fn attach(&self, parent_time: T) -> String {
+        format!("{}:{}", self.newick, self.time - parent_time)
+    }
+}
+
+enum Event {
+    Sampled,
+    Infected(usize),
+}
+
+impl<G, T: Timestamp> Outbreak<G, T> {
+    /// Print the transmission chains as time-scaled phylogenies in Newick format.
+    ///
+    /// Each case is a tip labelled as in [`write_fasta`](Outbreak::write_fasta), placed at the
+    /// time the case was sampled (or infected, for cases that were never sampled). Each
+    /// transmission event is an internal node where the lineage of the new case splits from the
+    /// lineage of its infector. Branch lengths are in simulation time steps.
+    ///
+    /// One tree is written per line for each independent outbreak.
+    pub fn write_newick<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        for (_, tree) in self.trees(case_label) {
+            writeln!(writer, "{};", tree)?;
+        }
+        Ok(())
+    }
+
+    /// Print the transmission chains as time-scaled phylogenies in NEXUS format.
+    ///
+    /// The trees are the same as for [`write_newick`](Outbreak::write_newick), but each tip is
+    /// annotated with the disease history of the case in the BEAST comment format, e.g.
+    /// `case000001[&outbreak=0,infected=3,reported=6,sampled=6]`, followed by the `region` of
+    /// the case if it has one.
+    pub fn write_nexus<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        let outbreaks = get_cluster_ids(&self.source);
+        let label = |i: usize| {
+            let history = &self.history[i];
+            let mut label = format!(
+                "{}[&outbreak={},infected={}",
+                case_label(i),
+                outbreaks[i],
+                history.infected
+            );
+            if let Some(reported) = history.reported {
+                label.push_str(&format!(",reported={}", reported));
+            }
+            if let Some(sampled) = history.sampled {
+                label.push_str(&format!(",sampled={}", sampled));
+            }
+            if let Some(region) = &self.region[i] {
+                label.push_str(&format!(",region={}", region));
+            }
+            label.push(']');
+            label
+        };
+
+        writeln!(writer, "#NEXUS")?;
+        writeln!(writer, "BEGIN TREES;")?;
+        for (root, tree) in self.trees(label) {
+            writeln!(
+                writer,
+                "\tTREE outbreak{} = [&R] {};",
+                outbreaks[root], tree
+            )?;
+        }
+        writeln!(writer, "END;")
+    }
+
+    /// Build one Newick tree for each case without an infector.
+    fn trees<F: Fn(usize) -> String>(&self, label: F) -> Vec<(usize, String)> {
+        let n = self.source.len();
+
+        let mut events: Vec<Vec<(T, Event)>> = (0..n)
+            .map(|i| vec![(self.tip_time(i), Event::Sampled)])
+            .collect();
+        for (i, source) in self.source.iter().enumerate() {
+            if let Some(source) = source {
+                events[*source as usize].push((self.history[i].infected, Event::Infected(i)));
+            }
+        }
+
+        // infectors always precede the cases they infect, so working backwards ensures that all
+        // subtrees of a case are built before the case itself
+        let mut subtrees: Vec<Option<Subtree<T>>> = (0..n).map(|_| None).collect();
+        for i in (0..n).rev() {
+            let mut case_events = std::mem::take(&mut events[i]);
+            case_events.sort_by(|a, b| a.0.cmp_total(&b.0));
+
+            let mut lineage: Option<Subtree<T>> = None;
+            for (time, event) in case_events.into_iter().rev() {
+                let branch = match event {
+                    Event::Sampled => Subtree {
+                        newick: label(i),
+                        time,
+                    },
+                    Event::Infected(j) => subtrees[j].take().unwrap(),
+                };
+                lineage = Some(match lineage {
+                    None => branch,
+                    Some(lineage) => Subtree {
+                        newick: format!("({},{})", branch.attach(time), lineage.attach(time)),
+                        time,
+                    },
+                });
+            }
+            subtrees[i] = lineage;
+        }
+
+        subtrees
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, tree)| Some((i, tree?.newick)))
+            .collect()
+    }
+
+    fn tip_time(&self, i: usize) -> T {
+        let history = &self.history[i];
+        history.sampled.unwrap_or(history.infected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::case::History;
+    use crate::Time;
+
+    fn history(infected: Time, sampled: Option<Time>) -> History {
+        History {
+            infected,
+            infectious_onset: infected,
+            infectious_peak: infected,
+            recovered: infected,
+            reported: sampled,
+            symptom_onset: None,
+            sampled,
+        }
+    }
+
+    fn outbreak() -> Outbreak<()> {
+        Outbreak {
+            source: vec![None, Some(0), Some(0), Some(1), None],
+            history: vec![
+                history(0, Some(4)),
+                history(2, Some(6)),
+                history(3, None),
+                history(5, Some(7)),
+                history(1, Some(2)),
+            ],
+            genome: vec![(); 5],
+            sampled_genome: vec![(); 5],
+            clock_rate: vec![0.0; 5],
+            setting: vec![None; 5],
+            region: vec![None; 5],
+        }
+    }
+
+    #[test]
+    fn test_newick() {
+        let mut out = Vec::new();
+        outbreak().write_newick(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "((case000003:2,case000001:1):3,(case000002:0,case000000:1):1);\ncase000004;\n"
+        );
+    }
+
+    #[test]
+    fn test_nexus() {
+        let mut out = Vec::new();
+        outbreak().write_nexus(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("#NEXUS\nBEGIN TREES;\n"));
+        assert!(out.contains("case000002[&outbreak=0,infected=3]:0"));
+        assert!(out.contains(
+            "\tTREE outbreak1 = [&R] case000004[&outbreak=1,infected=1,reported=2,sampled=2];\n"
+        ));
+        assert!(out.ends_with("END;\n"));
+    }
+}